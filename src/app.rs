@@ -1,20 +1,47 @@
-use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
 use eframe::egui;
 use egui::Image;
-use egui_plot::{Bar, BarChart, Legend, Line, Plot};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use egui_plot::{Bar, BarChart, Legend, Line, Plot, Points};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
 
 use crate::{
-    database::Database,
-    models::{DeadlineChange, Direction, MoneyType, Person, PersonStats, SortBy, Transaction},
+    database::{Archive, Database},
+    models::{
+        local_to_utc, AuditAction, CurrencyStats, DeadlineChange, Direction, MoneyType, Person,
+        PersonStats, SortBy, Transaction,
+    },
+    report,
+    saver::BackgroundSaver,
+    settings::{
+        default_currency_color, AnalysisSection, SaveMode, Settings, StartupTab, StorageBackend,
+        SymbolPosition,
+    },
+    stats::{
+        self, calculate_avg_return_time, calculate_currency_totals, calculate_deadline_shift,
+        calculate_promise_keeping_rate, calculate_reliability_score, match_deadline_returns,
+    },
+    storage,
 };
 
-#[derive(PartialEq)]
+const SEARCH_DEBOUNCE_SECS: f64 = 0.15;
+const DISK_CHECK_INTERVAL_SECS: f64 = 2.0;
+const SAVE_RETRY_SECS: f64 = 5.0;
+const THUMBNAIL_SIZE: f32 = 24.0;
+const MAX_NAME_CHARS: usize = 24;
+const NO_RATES_NOTE: &str =
+    "Amounts in different currencies are not added up: no exchange rates are configured.";
+const ATTACHMENT_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "bmp", "pdf"];
+
+#[derive(Clone, Copy, PartialEq)]
 enum Tab {
     AddTransaction,
     Analysis,
     Transactions,
+    Reminders,
+    Settings,
 }
 
 impl Default for Tab {
@@ -23,8 +50,116 @@ impl Default for Tab {
     }
 }
 
+impl Tab {
+    fn on_launch(settings: &Settings) -> Self {
+        let startup_tab = match settings.startup_tab {
+            StartupTab::LastUsed => settings.last_tab,
+            tab => tab,
+        };
+        match startup_tab {
+            StartupTab::Analysis => Tab::Analysis,
+            StartupTab::History => Tab::Transactions,
+            StartupTab::Reminders => Tab::Reminders,
+            StartupTab::Settings => Tab::Settings,
+            StartupTab::AddTransaction | StartupTab::LastUsed => Tab::AddTransaction,
+        }
+    }
+
+    fn as_startup_tab(self) -> StartupTab {
+        match self {
+            Tab::AddTransaction => StartupTab::AddTransaction,
+            Tab::Analysis => StartupTab::Analysis,
+            Tab::Transactions => StartupTab::History,
+            Tab::Reminders => StartupTab::Reminders,
+            Tab::Settings => StartupTab::Settings,
+        }
+    }
+}
+
+/// Findings of "Check data"; transactions are referred to by id.
+struct IntegrityReport {
+    /// Person, currency, "returned" or "repaid", and how much more came back than went out.
+    over_returns: Vec<(String, MoneyType, &'static str, f64)>,
+    future_dated: Vec<u64>,
+    missing_attachments: Vec<u64>,
+    orphaned_files: Vec<PathBuf>,
+}
+
+impl IntegrityReport {
+    fn is_empty(&self) -> bool {
+        self.over_returns.is_empty()
+            && self.future_dated.is_empty()
+            && self.missing_attachments.is_empty()
+            && self.orphaned_files.is_empty()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum EditWindow {
+    Transaction,
+    Deadline,
+}
+
+/// Edit window fields, applied to the transaction only on Save.
+#[derive(Clone, PartialEq)]
+struct EditDraft {
+    id: u64,
+    person_name: String,
+    amount: String,
+    money_type: MoneyType,
+    direction: Direction,
+    date: NaiveDate,
+    hour: u32,
+    minute: u32,
+    tags: String,
+    note: String,
+    attachment_path: Option<String>,
+}
+
+impl EditDraft {
+    fn new(t: &Transaction) -> Self {
+        let local = t.local_datetime();
+        Self {
+            id: t.id,
+            person_name: t.person.name.clone(),
+            amount: t.amount.to_string(),
+            money_type: t.money_type,
+            direction: t.direction,
+            date: local.date(),
+            hour: local.hour(),
+            minute: local.minute(),
+            tags: t
+                .tags
+                .iter()
+                .map(|tag| format!("#{}", tag))
+                .collect::<Vec<_>>()
+                .join(" "),
+            note: t.note.clone(),
+            attachment_path: t.attachment_path.clone(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct SplitShare {
+    name: String,
+    share: String,
+}
+
+enum BulkAction {
+    Tag,
+    Delete,
+}
+
+enum Thumbnail {
+    Loading,
+    Ready(egui::TextureHandle),
+    Failed,
+}
+
 pub struct BankingApp {
     db: Database,
+    settings: Settings,
 
     person_name: String,
     amount: String,
@@ -36,28 +171,129 @@ pub struct BankingApp {
     has_expected_return: bool,
     expected_return_date: NaiveDate,
     attachment_path: Option<String>,
+    attachment_copy: Option<(String, Receiver<Result<String, String>>)>,
 
     current_tab: Tab,
     status_message: String,
 
     pub logo_texture: Option<egui::TextureHandle>,
 
+    search_input: String,
     search_query: String,
+    search_edited_at: Option<f64>,
     sort_by: SortBy,
 
     edit_transaction_index: Option<usize>,
+    edit_draft: Option<EditDraft>,
+    /// Window asking whether to throw away its unsaved edits.
+    confirm_discard: Option<EditWindow>,
     attachment_textures: HashMap<String, egui::TextureHandle>,
+    thumbnails: HashMap<String, Thumbnail>,
+    missing_attachments: HashSet<String>,
+    thumbnail_sender: Sender<(String, Option<egui::ColorImage>)>,
+    thumbnail_receiver: Receiver<(String, Option<egui::ColorImage>)>,
+    open_error_sender: Sender<String>,
+    open_error_receiver: Receiver<String>,
     viewing_attachment: Option<String>,
 
     editing_deadline_for: Option<usize>,
     temp_new_deadline: NaiveDate,
+    editing_reminder_for: Option<usize>,
+    temp_reminder: String,
+
+    show_audit_log: bool,
+    show_deadline_history: bool,
+    qif_export: Option<MoneyType>,
+    /// Open while choosing CSV export options; holds whether to add running totals.
+    csv_export: Option<bool>,
+    deadline_history_filter: String,
+    deadline_history_oldest_first: bool,
+    reset_plots: HashSet<&'static str>,
+    selected_transactions: HashSet<usize>,
+    bulk_tag_input: String,
+    confirm_bulk_delete: bool,
+    show_archive: bool,
+    archive: Option<Archive>,
+    include_archive: bool,
+    hide_settled_people: bool,
+    analysis_range: bool,
+    analysis_from: NaiveDate,
+    analysis_to: NaiveDate,
+    /// Currency shown in the outstanding-by-person chart; the first one in use if unset.
+    outstanding_currency: Option<MoneyType>,
+    archive_before: NaiveDate,
+    /// Title, suggested file name and body of a plain-text report being shown.
+    text_report: Option<(&'static str, &'static str, String)>,
+    pending_import: Option<Vec<Transaction>>,
+    confirm_import_replace: bool,
+    /// Confirmation word typed so far while the clear-all-data window is open.
+    clear_data_confirm: Option<String>,
+
+    group_by_month: bool,
+    compact_people: bool,
+    show_duplicates: bool,
+    duplicate_choices: HashMap<String, String>,
+    expanded_people: HashSet<String>,
+    person_detail: Option<Person>,
+
+    split_mode: bool,
+    form_linked_to: Option<u64>,
+    confirm_large_amount: bool,
+    split_equal: bool,
+    split_people: Vec<SplitShare>,
+    /// Further parts of the same entry paid in other currencies.
+    extra_amounts: Vec<(String, MoneyType)>,
+    note: String,
+
+    locked: bool,
+    last_activity: f64,
+    unlock_input: String,
+    unlock_failed: bool,
+    new_passcode: String,
+    ui_scale_applied: bool,
+
+    saver: BackgroundSaver,
+    disk_conflict: bool,
+    last_disk_check: f64,
+
+    unsaved_changes: bool,
+    last_save_time: f64,
+    last_save: Option<Result<DateTime<Utc>, String>>,
+    footer_totals: Option<Vec<(MoneyType, f64)>>,
+    confirm_quit: bool,
+    detached_people: Vec<String>,
+    integrity_report: Option<IntegrityReport>,
+    /// Problems found while loading, shown as a dismissable banner.
+    startup_warnings: Vec<String>,
 }
 
 impl Default for BankingApp {
     fn default() -> Self {
         let now = Local::now();
+        let (thumbnail_sender, thumbnail_receiver) = mpsc::channel();
+        let (open_error_sender, open_error_receiver) = mpsc::channel();
+        let db = Database::load();
+        let mut startup_warnings = Database::check_data_dirs();
+        if let Some(warning) = restore_warning(&db) {
+            startup_warnings.insert(0, warning);
+        }
+        let missing_attachments = find_missing_attachments(&db.transactions);
+        let status_message = if missing_attachments.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "⚠️ {} attachment file(s) are missing from disk",
+                missing_attachments.len()
+            )
+        };
+        let settings = Settings::load();
+        let current_tab = Tab::on_launch(&settings);
         Self {
-            db: Database::load(),
+            startup_warnings,
+            last_save: db.saved_at.map(Ok),
+            footer_totals: None,
+            db,
+            settings,
             person_name: String::new(),
             amount: String::new(),
             money_type: MoneyType::GEL,
@@ -68,30 +304,125 @@ impl Default for BankingApp {
             has_expected_return: false,
             expected_return_date: now.date_naive(),
             attachment_path: None,
-            current_tab: Tab::AddTransaction,
-            status_message: String::new(),
+            attachment_copy: None,
+            split_mode: false,
+            form_linked_to: None,
+            confirm_large_amount: false,
+            split_equal: true,
+            split_people: vec![SplitShare::default(), SplitShare::default()],
+            extra_amounts: Vec::new(),
+            note: String::new(),
+            current_tab,
+            status_message,
             logo_texture: None,
+            search_input: String::new(),
             search_query: String::new(),
+            search_edited_at: None,
             sort_by: SortBy::DateNewest,
             edit_transaction_index: None,
+            edit_draft: None,
+            confirm_discard: None,
             attachment_textures: HashMap::new(),
+            thumbnails: HashMap::new(),
+            missing_attachments,
+            thumbnail_sender,
+            thumbnail_receiver,
+            open_error_sender,
+            open_error_receiver,
             viewing_attachment: None,
             editing_deadline_for: None,
             temp_new_deadline: now.date_naive(),
+            editing_reminder_for: None,
+            temp_reminder: String::new(),
+            show_audit_log: false,
+            show_deadline_history: false,
+            qif_export: None,
+            csv_export: None,
+            deadline_history_filter: String::new(),
+            deadline_history_oldest_first: false,
+            reset_plots: HashSet::new(),
+            selected_transactions: HashSet::new(),
+            bulk_tag_input: String::new(),
+            confirm_bulk_delete: false,
+            show_archive: false,
+            archive: None,
+            include_archive: false,
+            hide_settled_people: false,
+            analysis_range: false,
+            analysis_from: now.date_naive() - chrono::Duration::days(90),
+            analysis_to: now.date_naive(),
+            outstanding_currency: None,
+            archive_before: now.date_naive() - chrono::Duration::days(90),
+            text_report: None,
+            pending_import: None,
+            confirm_import_replace: false,
+            clear_data_confirm: None,
+            group_by_month: false,
+            compact_people: false,
+            show_duplicates: false,
+            duplicate_choices: HashMap::new(),
+            expanded_people: HashSet::new(),
+            person_detail: None,
+            locked: false,
+            last_activity: 0.0,
+            unlock_input: String::new(),
+            unlock_failed: false,
+            new_passcode: String::new(),
+            ui_scale_applied: false,
+            saver: BackgroundSaver::new(),
+            disk_conflict: false,
+            last_disk_check: 0.0,
+            unsaved_changes: false,
+            last_save_time: 0.0,
+            confirm_quit: false,
+            detached_people: Vec::new(),
+            integrity_report: None,
         }
     }
 }
 
 impl eframe::App for BankingApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if !self.ui_scale_applied {
+            ctx.set_zoom_factor(self.settings.ui_scale);
+            self.ui_scale_applied = true;
+        }
+
+        self.track_inactivity(ctx);
+        self.apply_debounced_search(ctx);
+        self.poll_saves(ctx);
+        self.poll_attachment_copy(ctx);
+        self.poll_thumbnails(ctx);
+        self.poll_open_errors();
+        self.check_disk_changes(ctx);
+        self.flush_pending_save(ctx);
+        self.handle_close_request(ctx);
+
+        if self.locked {
+            self.show_lock_screen(ctx);
+            return;
+        }
+
         if let Some(ref path) = self.viewing_attachment.clone() {
-            egui::Window::new("📷 Attachment Viewer")
+            egui::Window::new(format!("{} Attachment Viewer", attachment_icon(path)))
+                .id(egui::Id::new("attachment_viewer"))
                 .collapsible(false)
                 .resizable(true)
                 .default_width(600.0)
                 .show(ctx, |ui| {
                     ui.vertical_centered(|ui| {
-                        if let Some(texture) = self.attachment_textures.get(path) {
+                        if is_pdf(path) {
+                            ui.label(
+                                egui::RichText::new(
+                                    PathBuf::from(path)
+                                        .file_name()
+                                        .and_then(|n| n.to_str())
+                                        .unwrap_or("file"),
+                                )
+                                .strong(),
+                            );
+                            ui.label("PDF documents can't be previewed here.");
+                        } else if let Some(texture) = self.attachment_textures.get(path) {
                             let max_size = egui::vec2(800.0, 600.0);
                             let img_size = texture.size_vec2();
                             let scale = (max_size.x / img_size.x)
@@ -104,13 +435,28 @@ impl eframe::App for BankingApp {
                             ui.label("Failed to load image");
                         }
 
-                        if ui.button("Close").clicked() {
-                            self.viewing_attachment = None;
-                        }
+                        ui.horizontal(|ui| {
+                            if ui.button("🗗 Open externally").clicked() {
+                                self.open_externally(path);
+                            }
+                            if ui.button("Close").clicked() {
+                                self.viewing_attachment = None;
+                            }
+                        });
                     });
                 });
         }
 
+        self.show_person_detail(ctx);
+        self.show_detached_people(ctx);
+        self.show_import_window(ctx);
+        self.show_clear_data_window(ctx);
+        self.show_integrity_window(ctx);
+        self.show_deadline_window(ctx);
+        self.show_reminder_window(ctx);
+
+        self.show_footer(ctx);
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.vertical(|ui| {
                 ui.horizontal(|ui| {
@@ -125,15 +471,46 @@ impl eframe::App for BankingApp {
                             .strong(),
                     );
                     ui.heading("💰💸💰");
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if self.saver.is_busy() {
+                            ui.spinner();
+                            ui.label(egui::RichText::new("Saving…").weak());
+                        }
+
+                        if self.settings.save_mode != SaveMode::Immediate {
+                            if ui
+                                .add_enabled(self.unsaved_changes, egui::Button::new("💾 Save"))
+                                .on_hover_text("Ctrl+S")
+                                .clicked()
+                            {
+                                self.save_now(ui.input(|i| i.time));
+                            }
+                            if self.unsaved_changes {
+                                ui.colored_label(
+                                    egui::Color32::from_rgb(255, 200, 100),
+                                    "● Unsaved changes",
+                                );
+                            }
+                        }
+                    });
                 });
 
                 ui.add_space(10.0);
                 ui.separator();
                 ui.add_space(10.0);
 
+                if self.disk_conflict {
+                    self.show_disk_conflict_banner(ui);
+                }
+
+                if !self.startup_warnings.is_empty() {
+                    self.show_startup_warnings(ui);
+                }
+
                 ui.horizontal(|ui| {
                     let available = ui.available_width();
-                    let total_btn_width = 450.0;
+                    let total_btn_width = 750.0;
                     let margin = (available - total_btn_width) / 2.0;
                     ui.add_space(margin);
 
@@ -211,8 +588,67 @@ impl eframe::App for BankingApp {
                     {
                         self.current_tab = Tab::Transactions;
                     }
+
+                    let btn_style = |selected: bool| -> egui::Button {
+                        let color = if selected {
+                            egui::Color32::from_rgb(230, 190, 60)
+                        } else {
+                            egui::Color32::from_rgb(130, 105, 40)
+                        };
+                        let text_color = egui::Color32::WHITE;
+
+                        egui::Button::new(
+                            egui::RichText::new("🔔 Reminders")
+                                .size(14.0)
+                                .color(text_color)
+                                .strong(),
+                        )
+                        .fill(color)
+                        .min_size([140.0, 40.0].into())
+                    };
+
+                    if ui
+                        .add(btn_style(self.current_tab == Tab::Reminders))
+                        .clicked()
+                    {
+                        self.current_tab = Tab::Reminders;
+                    }
+
+                    let btn_style = |selected: bool| -> egui::Button {
+                        let color = if selected {
+                            egui::Color32::from_rgb(170, 170, 190)
+                        } else {
+                            egui::Color32::from_rgb(90, 90, 110)
+                        };
+                        let text_color = egui::Color32::WHITE;
+
+                        egui::Button::new(
+                            egui::RichText::new("⚙ Settings")
+                                .size(14.0)
+                                .color(text_color)
+                                .strong(),
+                        )
+                        .fill(color)
+                        .min_size([140.0, 40.0].into())
+                    };
+
+                    if ui
+                        .add(btn_style(self.current_tab == Tab::Settings))
+                        .clicked()
+                    {
+                        self.current_tab = Tab::Settings;
+                    }
                 });
 
+                if self.settings.startup_tab == StartupTab::LastUsed
+                    && self.settings.last_tab != self.current_tab.as_startup_tab()
+                {
+                    self.settings.last_tab = self.current_tab.as_startup_tab();
+                    if let Err(e) = self.settings.save() {
+                        self.status_message = format!("❌ Error saving settings: {}", e);
+                    }
+                }
+
                 ui.add_space(15.0);
                 ui.separator();
                 ui.add_space(15.0);
@@ -221,6 +657,8 @@ impl eframe::App for BankingApp {
                     Tab::AddTransaction => self.show_add_transaction(ui),
                     Tab::Analysis => self.show_analysis(ui),
                     Tab::Transactions => self.show_transactions(ui, ctx),
+                    Tab::Reminders => self.show_reminders(ui),
+                    Tab::Settings => self.show_settings(ui),
                 }
             });
         });
@@ -228,655 +666,3965 @@ impl eframe::App for BankingApp {
 }
 
 impl BankingApp {
-    fn show_add_transaction(&mut self, ui: &mut egui::Ui) {
-        ui.vertical_centered(|ui| {
-            ui.add_space(20.0);
-            ui.heading(
-                egui::RichText::new("Add New Transaction")
-                    .size(24.0)
-                    .strong(),
-            );
-            ui.add_space(20.0);
-
-            let max_width = 500.0;
-            ui.allocate_ui_with_layout(
-                egui::vec2(max_width, ui.available_height()),
-                egui::Layout::top_down_justified(egui::Align::Center),
-                |ui| {
-                    egui::Grid::new("transaction_form")
-                        .num_columns(2)
-                        .spacing([40.0, 15.0])
-                        .striped(true)
-                        .show(ui, |ui| {
-                            ui.label(egui::RichText::new("👤 Person:").size(14.0));
-                            ui.text_edit_singleline(&mut self.person_name);
-                            ui.end_row();
-
-                            ui.label(egui::RichText::new("💵 Amount:").size(14.0));
-                            ui.text_edit_singleline(&mut self.amount);
-                            ui.end_row();
-
-                            ui.label(egui::RichText::new("💱 Currency:").size(14.0));
-                            egui::ComboBox::from_id_source("money_type")
-                                .selected_text(format!("{:?}", self.money_type))
-                                .show_ui(ui, |ui| {
-                                    ui.selectable_value(
-                                        &mut self.money_type,
-                                        MoneyType::GEL,
-                                        "GEL",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.money_type,
-                                        MoneyType::USD,
-                                        "USD",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.money_type,
-                                        MoneyType::EUR,
-                                        "EUR",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.money_type,
-                                        MoneyType::GBP,
-                                        "GBP",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.money_type,
-                                        MoneyType::RUB,
-                                        "RUB",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.money_type,
-                                        MoneyType::Other,
-                                        "Other",
-                                    );
-                                });
-                            ui.end_row();
-
-                            ui.label(egui::RichText::new("🔄 Direction:").size(14.0));
-                            egui::ComboBox::from_id_source("direction")
-                                .selected_text(format!("{:?}", self.direction))
-                                .show_ui(ui, |ui| {
-                                    ui.selectable_value(
-                                        &mut self.direction,
-                                        Direction::Lent,
-                                        "Lent (I gave)",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.direction,
-                                        Direction::Borrowed,
-                                        "Borrowed (I received)",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.direction,
-                                        Direction::Returned,
-                                        "Returned (They gave back)",
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.direction,
-                                        Direction::Repaid,
-                                        "Repaid (I gave back)",
-                                    );
-                                });
-                            ui.end_row();
-
-                            ui.label(egui::RichText::new("📅 Date:").size(14.0));
-                            ui.add(egui_extras::DatePickerButton::new(&mut self.selected_date));
-                            ui.end_row();
-
-                            ui.label(egui::RichText::new("⏰ Time:").size(14.0));
-                            ui.horizontal(|ui| {
-                                ui.add(
-                                    egui::DragValue::new(&mut self.selected_hour)
-                                        .clamp_range(0..=23),
-                                );
-                                ui.label(":");
-                                ui.add(
-                                    egui::DragValue::new(&mut self.selected_minute)
-                                        .clamp_range(0..=59),
-                                );
-                            });
-                            ui.end_row();
-
-                            ui.label(egui::RichText::new("📖 Expected Return:").size(14.0));
-                            ui.horizontal(|ui| {
-                                let can_set_expected =
-                                    matches!(self.direction, Direction::Lent | Direction::Borrowed);
+    fn track_inactivity(&mut self, ctx: &egui::Context) {
+        let (now, had_input) = ctx.input(|i| {
+            (
+                i.time,
+                !i.events.is_empty() || i.pointer.is_moving() || i.pointer.any_down(),
+            )
+        });
 
-                                if can_set_expected {
-                                    ui.checkbox(&mut self.has_expected_return, "Set date");
-                                    if self.has_expected_return {
-                                        ui.add(egui_extras::DatePickerButton::new(
-                                            &mut self.expected_return_date,
-                                        ));
-                                    }
-                                } else {
-                                    ui.label(egui::RichText::new("(N/A)").weak());
-                                    self.has_expected_return = false;
-                                }
-                            });
-                            ui.end_row();
+        if had_input || self.last_activity == 0.0 {
+            self.last_activity = now;
+        }
 
-                            ui.label(egui::RichText::new("📎 Attachment:").size(14.0));
-                            ui.horizontal(|ui| {
-                                if ui.button("📁 Browse...").clicked() {
-                                    if let Some(path) = rfd::FileDialog::new()
-                                        .add_filter("Images", &["png", "jpg", "jpeg", "gif", "bmp"])
-                                        .pick_file()
-                                    {
-                                        self.attachment_path =
-                                            Some(path.to_string_lossy().to_string());
-                                    }
-                                }
+        if self.locked {
+            return;
+        }
 
-                                if let Some(ref path) = self.attachment_path {
-                                    ui.label(
-                                        PathBuf::from(path)
-                                            .file_name()
-                                            .and_then(|n| n.to_str())
-                                            .unwrap_or("file"),
-                                    );
-                                    if ui.small_button("❌").clicked() {
-                                        self.attachment_path = None;
-                                    }
-                                } else {
-                                    ui.label(egui::RichText::new("None").weak());
-                                }
-                            });
-                            ui.end_row();
-                        });
-                },
-            );
+        if let Some(timeout) = self.settings.auto_lock_after_secs() {
+            let idle = now - self.last_activity;
+            if idle >= timeout {
+                self.locked = true;
+                self.viewing_attachment = None;
+            } else {
+                ctx.request_repaint_after(std::time::Duration::from_secs_f64(timeout - idle));
+            }
+        }
+    }
 
-            ui.add_space(20.0);
+    fn apply_debounced_search(&mut self, ctx: &egui::Context) {
+        if let Some(edited_at) = self.search_edited_at {
+            let elapsed = ctx.input(|i| i.time) - edited_at;
+            if elapsed >= SEARCH_DEBOUNCE_SECS {
+                self.search_query = self.search_input.clone();
+                self.search_edited_at = None;
+            } else {
+                ctx.request_repaint_after(std::time::Duration::from_secs_f64(
+                    SEARCH_DEBOUNCE_SECS - elapsed,
+                ));
+            }
+        }
+    }
 
-            if ui
-                .add(
-                    egui::Button::new(
-                        egui::RichText::new("✅ Add Transaction")
-                            .size(16.0)
-                            .strong()
-                            .color(egui::Color32::WHITE),
-                    )
-                    .fill(egui::Color32::from_rgb(100, 200, 100))
-                    .min_size([150.0, 45.0].into()),
-                )
-                .clicked()
-            {
-                if let Ok(amount) = self.amount.parse::<f64>() {
-                    if !self.person_name.trim().is_empty() && amount > 0.0 {
-                        let time =
-                            NaiveTime::from_hms_opt(self.selected_hour, self.selected_minute, 0)
-                                .unwrap_or_else(|| NaiveTime::from_hms_opt(0, 0, 0).unwrap());
-                        let datetime = NaiveDateTime::new(self.selected_date, time);
-
-                        let stored_attachment = if let Some(ref path) = self.attachment_path {
-                            match Database::copy_attachment_to_storage(path) {
-                                Ok(stored_path) => Some(stored_path),
-                                Err(e) => {
-                                    self.status_message =
-                                        format!("⚠️ Failed to copy attachment: {}", e);
-                                    None
-                                }
-                            }
-                        } else {
-                            None
-                        };
+    fn persist(&mut self) {
+        self.footer_totals = None;
+        if self.settings.save_mode == SaveMode::Immediate {
+            self.unsaved_changes = false;
+            self.saver.save(self.db.clone(), false);
+        } else {
+            self.unsaved_changes = true;
+        }
+    }
 
-                        let transaction = Transaction {
-                            person: Person {
-                                name: self.person_name.trim().to_string(),
-                            },
-                            amount,
-                            money_type: self.money_type,
-                            direction: self.direction,
-                            datetime,
-                            expected_return_date: if self.has_expected_return {
-                                Some(self.expected_return_date)
-                            } else {
-                                None
-                            },
-                            attachment_path: stored_attachment,
-                            deadline_changes: Vec::new(),
-                        };
+    fn save_now(&mut self, now: f64) {
+        self.last_save_time = now;
+        self.unsaved_changes = false;
+        self.saver.save(self.db.clone(), false);
+    }
 
-                        self.db.add_transaction(transaction);
-                        if let Err(e) = self.db.save() {
-                            self.status_message = format!("❌ Error saving: {}", e);
-                        } else {
-                            self.status_message = "✅ Transaction added successfully!".to_string();
-                            self.person_name.clear();
-                            self.amount.clear();
-                            self.has_expected_return = false;
-                            self.attachment_path = None;
-                        }
-                    } else {
-                        self.status_message =
-                            "⚠️ Invalid input: name required and amount must be positive"
-                                .to_string();
+    fn poll_saves(&mut self, ctx: &egui::Context) {
+        while let Some(result) = self.saver.try_result() {
+            match result {
+                Ok((revision, modified)) => {
+                    self.db.mark_synced(revision, modified);
+                    self.last_save = Some(Ok(Utc::now()));
+                    if !self.saver.is_busy() && !self.unsaved_changes {
+                        self.db.finish_saved();
                     }
-                } else {
-                    self.status_message = "⚠️ Invalid amount".to_string();
+                }
+                Err(e) => {
+                    self.status_message = format!("❌ Error saving: {}", e);
+                    self.last_save = Some(Err(e));
+                    self.last_save_time = ctx.input(|i| i.time);
+                    self.unsaved_changes = true;
                 }
             }
+        }
 
-            if !self.status_message.is_empty() {
-                ui.add_space(15.0);
-                let color = if self.status_message.starts_with("✅") {
-                    egui::Color32::GREEN
-                } else if self.status_message.starts_with("❌") {
-                    egui::Color32::RED
-                } else {
-                    egui::Color32::YELLOW
-                };
-                ui.colored_label(color, egui::RichText::new(&self.status_message).size(14.0));
-            }
-        });
+        if self.saver.is_busy() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
     }
 
-    fn show_analysis(&mut self, ui: &mut egui::Ui) {
-        if self.db.transactions.is_empty() {
-            ui.vertical_centered(|ui| {
-                ui.add_space(50.0);
-                ui.heading("🔭 No transactions yet");
-                ui.label("Add some transactions to see detailed analysis!");
-            });
+    fn flush_pending_save(&mut self, ctx: &egui::Context) {
+        let now = ctx.input(|i| i.time);
+
+        if self.unsaved_changes
+            && ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::S))
+        {
+            self.save_now(now);
             return;
         }
 
-        egui::ScrollArea::vertical()
-            .auto_shrink([false; 2])
-            .show(ui, |ui| {
-                let available_width = ui.available_width();
-                let content_width = (available_width - 40.0).min(1200.0);
-                let margin = (available_width - content_width) / 2.0;
+        // A conflict fails every save until the user picks a copy.
+        if !self.unsaved_changes
+            || self.settings.save_mode == SaveMode::Manual
+            || self.disk_conflict
+        {
+            return;
+        }
 
-                ui.add_space(15.0);
+        let interval = match self.settings.save_mode {
+            SaveMode::Debounced => self.settings.autosave_secs as f64,
+            // Immediate mode only gets here after a failed save: retry, but not every frame.
+            _ if matches!(self.last_save, Some(Err(_))) => SAVE_RETRY_SECS,
+            _ => 0.0,
+        };
+        let wait = interval - (now - self.last_save_time);
+        if wait <= 0.0 {
+            self.save_now(now);
+        } else {
+            ctx.request_repaint_after(std::time::Duration::from_secs_f64(wait));
+        }
+    }
 
-                ui.vertical_centered(|ui| {
-                    ui.heading(
-                        egui::RichText::new("Financial Analysis Dashboard")
-                            .size(24.0)
-                            .strong(),
-                    );
-                });
+    fn handle_close_request(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| i.viewport().close_requested()) && self.unsaved_changes {
+            if self.settings.save_mode == SaveMode::Manual {
+                ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+                self.confirm_quit = true;
+            } else {
+                self.save_now(ctx.input(|i| i.time));
+            }
+        }
 
-                ui.add_space(20.0);
+        if !self.confirm_quit {
+            return;
+        }
+
+        egui::Window::new("💾 Unsaved changes")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label("You have changes that have not been saved yet.");
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    if ui.button("💾 Save and quit").clicked() {
+                        self.save_now(ui.input(|i| i.time));
+                        self.confirm_quit = false;
+                        if !self.unsaved_changes {
+                            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                        }
+                    }
+                    if ui.button("🗑 Quit without saving").clicked() {
+                        self.unsaved_changes = false;
+                        self.confirm_quit = false;
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.confirm_quit = false;
+                    }
+                });
+            });
+    }
 
-                let mut balances_by_currency: HashMap<MoneyType, f64> = HashMap::new();
-                let mut total_lent = 0.0;
-                let mut total_borrowed = 0.0;
-                let mut total_returned = 0.0;
-                let mut total_repaid = 0.0;
+    fn show_footer(&mut self, ctx: &egui::Context) {
+        let totals = self.footer_totals.get_or_insert_with(|| {
+            let by_currency = calculate_currency_totals(&self.db.transactions);
+            self.settings
+                .currencies()
+                .into_iter()
+                .filter_map(|money_type| Some((money_type, by_currency.get(&money_type)?.net())))
+                .collect()
+        });
 
-                for currency in [MoneyType::GEL, MoneyType::USD, MoneyType::EUR] {
-                    balances_by_currency.insert(currency, 0.0);
+        egui::TopBottomPanel::bottom("footer").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new(format!("{} transaction(s)", self.db.transactions.len()))
+                        .small(),
+                );
+                for (money_type, net) in totals.iter() {
+                    ui.separator();
+                    ui.label(
+                        egui::RichText::new(self.settings.format_balance_code(*money_type, *net))
+                            .small()
+                            .color(self.settings.balance_color(*net)),
+                    );
                 }
 
-                for t in &self.db.transactions {
-                    let balance = balances_by_currency.entry(t.money_type).or_insert(0.0);
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    match &self.last_save {
+                        Some(Ok(saved_at)) => ui.label(
+                            egui::RichText::new(format!(
+                                "Last saved: {}",
+                                saved_at.with_timezone(&Local).format("%H:%M:%S")
+                            ))
+                            .small()
+                            .weak(),
+                        ),
+                        Some(Err(e)) => ui.label(
+                            egui::RichText::new(format!("❌ Last save failed: {}", e))
+                                .small()
+                                .color(egui::Color32::from_rgb(255, 100, 100)),
+                        ),
+                        None => ui.label(egui::RichText::new("Not saved yet").small().weak()),
+                    };
+                });
+            });
+        });
+    }
 
-                    match t.direction {
-                        Direction::Lent => {
-                            *balance -= t.amount;
-                            total_lent += t.amount;
-                        }
-                        Direction::Borrowed => {
-                            *balance += t.amount;
-                            total_borrowed += t.amount;
-                        }
-                        Direction::Returned => {
-                            *balance += t.amount;
-                            total_returned += t.amount;
-                        }
-                        Direction::Repaid => {
-                            *balance -= t.amount;
-                            total_repaid += t.amount;
-                        }
-                    }
-                }
+    fn check_disk_changes(&mut self, ctx: &egui::Context) {
+        let now = ctx.input(|i| i.time);
+        if self.saver.is_busy() {
+            // `poll_saves` keeps repainting until the save is done.
+            return;
+        }
+        if now - self.last_disk_check >= DISK_CHECK_INTERVAL_SECS {
+            self.last_disk_check = now;
+            self.disk_conflict = self.db.changed_on_disk();
+        }
+        let wait = DISK_CHECK_INTERVAL_SECS - (now - self.last_disk_check);
+        ctx.request_repaint_after(std::time::Duration::from_secs_f64(wait));
+    }
 
+    fn show_disk_conflict_banner(&mut self, ui: &mut egui::Ui) {
+        egui::Frame::none()
+            .fill(egui::Color32::from_rgb(90, 70, 20))
+            .rounding(6.0)
+            .inner_margin(10.0)
+            .show(ui, |ui| {
                 ui.horizontal(|ui| {
-                    ui.add_space(margin);
-                    ui.vertical(|ui| {
-                        ui.set_width(content_width);
-
-                        let card_min_width = 160.0;
-                        let card_spacing = 10.0;
-                        let cards_per_row = ((content_width + card_spacing)
-                            / (card_min_width + card_spacing))
-                            .floor()
-                            .max(1.0) as usize;
-
-                        egui::Grid::new("stats_grid")
-                            .spacing([card_spacing, card_spacing])
-                            .num_columns(cards_per_row)
-                            .show(ui, |ui| {
-                                let stats = [
-                                    (
-                                        "💸 Total Lent",
-                                        total_lent,
-                                        egui::Color32::from_rgb(255, 130, 130),
-                                    ),
-                                    (
-                                        "🔥 Total Borrowed",
-                                        total_borrowed,
-                                        egui::Color32::from_rgb(120, 160, 255),
-                                    ),
-                                    (
-                                        "✅ Total Returned",
-                                        total_returned,
-                                        egui::Color32::from_rgb(120, 220, 120),
-                                    ),
-                                    (
-                                        "💳 Total Repaid",
-                                        total_repaid,
-                                        egui::Color32::from_rgb(200, 255, 150),
-                                    ),
-                                ];
-
-                                for (idx, (label, value, color)) in stats.iter().enumerate() {
-                                    ui.group(|ui| {
-                                        ui.set_min_width(card_min_width);
-                                        ui.vertical_centered(|ui| {
-                                            ui.colored_label(
-                                                *color,
-                                                egui::RichText::new(*label).size(14.0).strong(),
-                                            );
-                                            ui.label(
-                                                egui::RichText::new(format!("{:.2}", value))
-                                                    .size(20.0)
-                                                    .strong(),
-                                            );
-                                        });
-                                    });
+                    ui.label(
+                        egui::RichText::new(
+                            "⚠️ The data file was changed elsewhere since it was loaded. \
+                             Saving is paused until you choose which copy to keep.",
+                        )
+                        .color(egui::Color32::WHITE),
+                    );
 
-                                    if (idx + 1) % cards_per_row == 0 {
-                                        ui.end_row();
-                                    }
-                                }
-                            });
-                    });
-                });
+                    if ui.button("🔄 Reload from disk").clicked() {
+                        self.db = Database::load();
+                        if self.archive.is_some() {
+                            self.archive = Some(self.db.archive());
+                        }
+                        self.startup_warnings.extend(restore_warning(&self.db));
+                        self.footer_totals = None;
+                        self.disk_conflict = false;
+                        self.unsaved_changes = false;
+                        self.selected_transactions.clear();
+                        self.edit_transaction_index = None;
+                        self.editing_deadline_for = None;
+                        self.editing_reminder_for = None;
+                        self.status_message = "✅ Reloaded data from disk".to_string();
+                    }
 
-                ui.add_space(25.0);
+                    if ui.button("💾 Overwrite with mine").clicked() {
+                        self.saver.save(self.db.clone(), true);
+                        self.disk_conflict = false;
+                        self.unsaved_changes = false;
+                        self.status_message = "✅ Saved over the changed file".to_string();
+                    }
+                });
+            });
+        ui.add_space(10.0);
+    }
 
+    fn show_startup_warnings(&mut self, ui: &mut egui::Ui) {
+        egui::Frame::none()
+            .fill(egui::Color32::from_rgb(110, 40, 40))
+            .rounding(6.0)
+            .inner_margin(10.0)
+            .show(ui, |ui| {
                 ui.horizontal(|ui| {
-                    ui.add_space(margin);
-                    ui.group(|ui| {
-                        ui.set_width(content_width);
-                        ui.vertical(|ui| {
-                            ui.heading(
-                                egui::RichText::new("💼 Current Net Balance by Currency")
-                                    .size(18.0)
-                                    .strong(),
+                    ui.vertical(|ui| {
+                        for warning in &self.startup_warnings {
+                            ui.label(
+                                egui::RichText::new(format!("⚠️ {}", warning))
+                                    .color(egui::Color32::WHITE),
                             );
-                            ui.add_space(10.0);
+                        }
+                    });
+                    if ui.button("Dismiss").clicked() {
+                        self.startup_warnings.clear();
+                    }
+                });
+            });
+        ui.add_space(10.0);
+    }
 
-                            let mut currencies: Vec<_> = balances_by_currency.iter().collect();
-                            currencies.sort_by(|a, b| {
-                                let abs_cmp =
-                                    b.1.abs()
-                                        .partial_cmp(&a.1.abs())
-                                        .unwrap_or(std::cmp::Ordering::Equal);
-                                if abs_cmp == std::cmp::Ordering::Equal {
-                                    format!("{:?}", a.0).cmp(&format!("{:?}", b.0))
-                                } else {
-                                    abs_cmp
-                                }
-                            });
+    fn show_lock_screen(&mut self, ctx: &egui::Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.add_space(120.0);
+                if let Some(tex) = &self.logo_texture {
+                    ui.add(Image::new(tex).fit_to_exact_size(egui::vec2(80.0, 80.0)));
+                }
+                ui.add_space(20.0);
+                ui.heading(egui::RichText::new("🔒 Locked").size(28.0).strong());
+                ui.label("The app was locked after a period of inactivity.");
+                ui.add_space(20.0);
 
-                            let card_width = 140.0;
-                            let card_spacing = 10.0;
-                            let num_cards = currencies.len() as f32;
-                            let total_width =
-                                (card_width * num_cards) + (card_spacing * (num_cards - 1.0));
-                            let left_padding = ((content_width - total_width) / 2.0).max(0.0);
+                let input = ui.add(
+                    egui::TextEdit::singleline(&mut self.unlock_input)
+                        .password(true)
+                        .hint_text("Passcode")
+                        .desired_width(150.0),
+                );
+                input.request_focus();
+                let submitted = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if self.unlock_failed {
+                    ui.colored_label(egui::Color32::from_rgb(255, 130, 130), "❌ Wrong passcode");
+                }
+                ui.add_space(10.0);
 
-                            ui.horizontal(|ui| {
-                                ui.add_space(left_padding);
-                                for (idx, (currency, balance)) in currencies.iter().enumerate() {
-                                    let color = if **balance > 0.0 {
-                                        egui::Color32::from_rgb(100, 200, 100)
-                                    } else if **balance < 0.0 {
-                                        egui::Color32::from_rgb(255, 120, 120)
-                                    } else {
-                                        egui::Color32::GRAY
-                                    };
+                if ui
+                    .add(
+                        egui::Button::new(
+                            egui::RichText::new("🔓 Unlock")
+                                .size(16.0)
+                                .strong()
+                                .color(egui::Color32::WHITE),
+                        )
+                        .fill(egui::Color32::from_rgb(100, 150, 255))
+                        .min_size([150.0, 45.0].into()),
+                    )
+                    .clicked()
+                    || submitted
+                {
+                    if self.settings.check_passcode(&self.unlock_input) {
+                        self.locked = false;
+                        self.unlock_failed = false;
+                        self.last_activity = ctx.input(|i| i.time);
+                    } else {
+                        self.unlock_failed = true;
+                    }
+                    self.unlock_input.clear();
+                }
+            });
+        });
+    }
 
-                                    ui.group(|ui| {
-                                        ui.set_width(card_width);
-                                        ui.vertical_centered(|ui| {
-                                            ui.label(format!("{:?}", currency));
-                                            ui.colored_label(
-                                                color,
-                                                egui::RichText::new(format!(
-                                                    "{}{:.2}",
-                                                    currency.symbol(),
-                                                    balance
-                                                ))
-                                                .size(18.0)
-                                                .strong(),
-                                            );
-                                        });
-                                    });
+    /// Open loans that have a deadline or a reminder, soonest due first.
+    fn show_reminders(&mut self, ui: &mut egui::Ui) {
+        let settled_amounts = self.calculate_settled_amounts();
+        let mut due: Vec<usize> = self
+            .db
+            .transactions
+            .iter()
+            .enumerate()
+            .filter(|(idx, t)| {
+                matches!(t.direction, Direction::Lent | Direction::Borrowed)
+                    && (t.expected_return_date.is_some() || t.reminder.is_some())
+                    && !t.settled
+                    && !self
+                        .settings
+                        .is_cleared(t.amount, settled_amounts.get(idx).copied().unwrap_or(0.0))
+            })
+            .map(|(idx, _)| idx)
+            .collect();
+        due.sort_by_key(|idx| {
+            let t = &self.db.transactions[*idx];
+            (
+                t.expected_return_date.is_none(),
+                t.expected_return_date,
+                t.datetime,
+            )
+        });
 
-                                    if idx < currencies.len() - 1 {
-                                        ui.add_space(card_spacing);
-                                    }
-                                }
-                            });
-                        });
-                    });
-                });
+        ui.vertical_centered(|ui| {
+            ui.heading(egui::RichText::new("🔔 Reminders").size(24.0).strong());
+        });
+        ui.add_space(15.0);
 
+        if due.is_empty() {
+            ui.vertical_centered(|ui| {
                 ui.add_space(30.0);
+                ui.label("No open loans with a deadline or a reminder.");
+            });
+            return;
+        }
 
-                ui.horizontal(|ui| {
-                    ui.add_space(margin);
+        let today = Local::now().date_naive();
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                for idx in due {
+                    let t = &self.db.transactions[idx];
                     ui.group(|ui| {
-                        ui.set_width(content_width);
-                        ui.vertical(|ui| {
-                            ui.heading(
-                                egui::RichText::new("📊 Balance Timeline")
-                                    .size(20.0)
-                                    .strong(),
+                        ui.horizontal(|ui| {
+                            full_name_on_hover(
+                                ui.label(egui::RichText::new(short_name(&t.person.name)).strong()),
+                                &t.person.name,
                             );
-                            ui.add_space(15.0);
-
-                            let timeline = self.generate_balance_timeline();
-
-                            Plot::new("balance_timeline")
-                                .legend(Legend::default().position(egui_plot::Corner::LeftTop))
-                                .show_axes([true, true])
-                                .height(400.0)
-                                .allow_scroll(false)
-                                .allow_zoom(true)
-                                .allow_drag(true)
-                                .width(content_width - 40.0)
-                                .show(ui, |plot_ui| {
-                                    for (idx, (currency, points)) in timeline.iter().enumerate() {
-                                        let color = match idx {
-                                            0 => egui::Color32::from_rgb(255, 100, 100),
-                                            1 => egui::Color32::from_rgb(100, 150, 255),
-                                            2 => egui::Color32::from_rgb(100, 220, 100),
-                                            3 => egui::Color32::from_rgb(255, 180, 50),
-                                            4 => egui::Color32::from_rgb(200, 100, 255),
-                                            _ => egui::Color32::GRAY,
-                                        };
+                            ui.label(format!(
+                                "{:?} {}",
+                                t.direction,
+                                self.settings.format_money(t.money_type, t.amount)
+                            ));
+                            ui.separator();
+
+                            match t.expected_return_date {
+                                Some(date) => {
+                                    let days = (date - today).num_days();
+                                    let (text, color) = match days {
+                                        d if d < 0 => (
+                                            format!("⏰ Overdue by {} day(s)", -d),
+                                            egui::Color32::from_rgb(255, 120, 120),
+                                        ),
+                                        0 => ("⏰ Due today".to_string(), egui::Color32::YELLOW),
+                                        d if d <= 7 => (
+                                            format!("📅 Due in {} day(s)", d),
+                                            egui::Color32::YELLOW,
+                                        ),
+                                        d => (
+                                            format!("📅 Due in {} days", d),
+                                            egui::Color32::LIGHT_BLUE,
+                                        ),
+                                    };
+                                    ui.colored_label(color, text)
+                                        .on_hover_text(date.format("%Y-%m-%d").to_string());
+                                }
+                                None => {
+                                    ui.label(egui::RichText::new("No deadline").weak());
+                                }
+                            }
+
+                            if let Some(reminder) = &t.reminder {
+                                ui.separator();
+                                ui.label(egui::RichText::new(format!("🔔 {}", reminder)).strong());
+                            }
 
-                                        let line = Line::new(points.clone())
-                                            .name(format!("{:?}", currency))
-                                            .stroke(egui::Stroke::new(3.0, color));
-                                        plot_ui.line(line);
+                            ui.with_layout(
+                                egui::Layout::right_to_left(egui::Align::Center),
+                                |ui| {
+                                    if ui
+                                        .small_button("🔔")
+                                        .on_hover_text("Edit reminder")
+                                        .clicked()
+                                    {
+                                        self.editing_reminder_for = Some(idx);
+                                        self.temp_reminder = t.reminder.clone().unwrap_or_default();
                                     }
-                                });
+                                    if ui
+                                        .small_button("📝")
+                                        .on_hover_text("Change deadline")
+                                        .clicked()
+                                    {
+                                        self.editing_deadline_for = Some(idx);
+                                        self.temp_new_deadline = t.expected_return_date.unwrap_or(
+                                            Local::now().date_naive() + chrono::Months::new(1),
+                                        );
+                                    }
+                                },
+                            );
                         });
                     });
-                });
+                }
+            });
+    }
 
-                ui.add_space(30.0);
+    fn show_settings(&mut self, ui: &mut egui::Ui) {
+        ui.vertical_centered(|ui| {
+            ui.add_space(20.0);
+            ui.heading(egui::RichText::new("Settings").size(24.0).strong());
+            ui.add_space(20.0);
 
-                ui.horizontal(|ui| {
-                    ui.add_space(margin);
-                    ui.vertical(|ui| {
-                        ui.set_width(content_width);
+            let mut changed = false;
+
+            egui::Grid::new("settings_form")
+                .num_columns(2)
+                .spacing([40.0, 15.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label(egui::RichText::new("🚀 Open on start:").size(14.0));
+                    egui::ComboBox::from_id_source("startup_tab")
+                        .selected_text(self.settings.startup_tab.label())
+                        .show_ui(ui, |ui| {
+                            for tab in StartupTab::ALL {
+                                changed |= ui
+                                    .selectable_value(
+                                        &mut self.settings.startup_tab,
+                                        tab,
+                                        tab.label(),
+                                    )
+                                    .changed();
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label(egui::RichText::new("🔑 Passcode:").size(14.0));
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.new_passcode)
+                                .password(true)
+                                .hint_text("at least 4 characters")
+                                .desired_width(140.0),
+                        );
+                        let label = if self.settings.passcode_hash.is_some() {
+                            "Change"
+                        } else {
+                            "Set"
+                        };
+                        if ui
+                            .add_enabled(
+                                self.new_passcode.chars().count() >= 4,
+                                egui::Button::new(label),
+                            )
+                            .clicked()
+                        {
+                            self.settings.set_passcode(&self.new_passcode);
+                            self.new_passcode.clear();
+                            self.status_message = "✅ Passcode saved".to_string();
+                            changed = true;
+                        }
+                        if self.settings.passcode_hash.is_some() && ui.button("Remove").clicked() {
+                            self.settings.passcode_hash = None;
+                            self.status_message =
+                                "⚠️ Passcode removed; auto-lock is off until a new one is set"
+                                    .to_string();
+                            changed = true;
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label(egui::RichText::new("🔒 Auto-lock:").size(14.0));
+                    ui.horizontal(|ui| {
+                        changed |= ui
+                            .add_enabled(
+                                self.settings.passcode_hash.is_some(),
+                                egui::Checkbox::new(
+                                    &mut self.settings.auto_lock_enabled,
+                                    "Lock after",
+                                ),
+                            )
+                            .on_disabled_hover_text("Set a passcode first")
+                            .changed();
+                        ui.add_enabled_ui(self.settings.auto_lock_enabled, |ui| {
+                            changed |= ui
+                                .add(
+                                    egui::DragValue::new(&mut self.settings.auto_lock_minutes)
+                                        .clamp_range(1..=240),
+                                )
+                                .changed();
+                        });
+                        ui.label("minutes of inactivity");
+                    });
+                    ui.end_row();
 
-                        let chart_min_width = 300.0;
-                        let use_single_column = content_width < (chart_min_width * 2.0 + 20.0);
+                    ui.label(egui::RichText::new("🔍 UI scale:").size(14.0));
+                    let scale_response = ui.add(
+                        egui::Slider::new(&mut self.settings.ui_scale, 0.75..=2.0)
+                            .step_by(0.05)
+                            .suffix("×"),
+                    );
+                    if scale_response.drag_stopped()
+                        || (scale_response.changed() && !scale_response.dragged())
+                    {
+                        self.ui_scale_applied = false;
+                        changed = true;
+                    }
+                    ui.end_row();
 
-                        if use_single_column {
-                            self.draw_outstanding_chart(ui, content_width);
-                            ui.add_space(20.0);
-                            self.draw_return_rate_chart(ui, content_width);
-                        } else {
-                            let chart_width = (content_width - 20.0) / 2.0;
-                            ui.columns(2, |columns| {
-                                self.draw_outstanding_chart(&mut columns[0], chart_width);
-                                self.draw_return_rate_chart(&mut columns[1], chart_width);
+                    ui.label(egui::RichText::new("🌓 Accessibility:").size(14.0));
+                    changed |= ui
+                        .checkbox(
+                            &mut self.settings.high_contrast,
+                            "High-contrast, colorblind-friendly palette",
+                        )
+                        .changed();
+                    ui.end_row();
+
+                    ui.label(egui::RichText::new("🎨 Chart colors:").size(14.0));
+                    ui.horizontal_wrapped(|ui| {
+                        for money_type in self.settings.currencies() {
+                            let mut color = self
+                                .settings
+                                .currency_colors
+                                .get(&money_type)
+                                .copied()
+                                .unwrap_or_else(|| default_currency_color(money_type));
+                            ui.label(self.settings.currency_code(money_type));
+                            if ui.color_edit_button_srgb(&mut color).changed() {
+                                self.settings.currency_colors.insert(money_type, color);
+                                changed = true;
+                            }
+                        }
+                        if !self.settings.currency_colors.is_empty()
+                            && ui.small_button("Reset").clicked()
+                        {
+                            self.settings.currency_colors.clear();
+                            changed = true;
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label(egui::RichText::new("🔣 Decimals:").size(14.0));
+                    ui.horizontal_wrapped(|ui| {
+                        for money_type in self.settings.currencies() {
+                            let mut decimals = self.settings.decimals(money_type);
+                            ui.label(self.settings.currency_code(money_type));
+                            if ui
+                                .add(egui::DragValue::new(&mut decimals).clamp_range(0..=4))
+                                .changed()
+                            {
+                                self.settings.decimal_places.insert(money_type, decimals);
+                                changed = true;
+                            }
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label(egui::RichText::new("💾 Saving:").size(14.0));
+                    ui.horizontal(|ui| {
+                        changed |= ui
+                            .radio_value(
+                                &mut self.settings.save_mode,
+                                SaveMode::Immediate,
+                                "After every change",
+                            )
+                            .changed();
+                        changed |= ui
+                            .radio_value(
+                                &mut self.settings.save_mode,
+                                SaveMode::Debounced,
+                                "At most every",
+                            )
+                            .changed();
+                        ui.add_enabled_ui(self.settings.save_mode == SaveMode::Debounced, |ui| {
+                            changed |= ui
+                                .add(
+                                    egui::DragValue::new(&mut self.settings.autosave_secs)
+                                        .clamp_range(1..=600)
+                                        .suffix(" s"),
+                                )
+                                .changed();
+                        });
+                        changed |= ui
+                            .radio_value(
+                                &mut self.settings.save_mode,
+                                SaveMode::Manual,
+                                "Only when I click Save",
+                            )
+                            .changed();
+                    });
+                    ui.end_row();
+
+                    ui.label(egui::RichText::new("💲 Currency symbol:").size(14.0));
+                    ui.horizontal(|ui| {
+                        changed |= ui
+                            .radio_value(
+                                &mut self.settings.symbol_position,
+                                SymbolPosition::Before,
+                                "Before (₾10.00)",
+                            )
+                            .changed();
+                        changed |= ui
+                            .radio_value(
+                                &mut self.settings.symbol_position,
+                                SymbolPosition::After,
+                                "After (10.00 ₾)",
+                            )
+                            .changed();
+                    });
+                    ui.end_row();
+
+                    ui.label(egui::RichText::new("💱 Other currency:").size(14.0));
+                    ui.horizontal(|ui| {
+                        changed |= ui
+                            .add(
+                                egui::TextEdit::singleline(&mut self.settings.other_currency_code)
+                                    .hint_text("Other")
+                                    .desired_width(80.0),
+                            )
+                            .changed();
+                        ui.label("shown instead of \"Other\"");
+                    });
+                    ui.end_row();
+
+                    ui.label(egui::RichText::new("✔ Paid back when:").size(14.0));
+                    ui.horizontal(|ui| {
+                        ui.label("less than");
+                        changed |= ui
+                            .add(
+                                egui::DragValue::new(&mut self.settings.cleared_tolerance)
+                                    .clamp_range(0.0..=100.0)
+                                    .speed(0.01)
+                                    .max_decimals(2),
+                            )
+                            .changed();
+                        ui.label("or");
+                        changed |= ui
+                            .add(
+                                egui::DragValue::new(&mut self.settings.cleared_tolerance_percent)
+                                    .clamp_range(0.0..=10.0)
+                                    .speed(0.1)
+                                    .max_decimals(1)
+                                    .suffix("%"),
+                            )
+                            .changed();
+                        ui.label("of the loan is left");
+                    });
+                    ui.end_row();
+
+                    ui.label(egui::RichText::new("🔢 Large amounts:").size(14.0));
+                    ui.horizontal(|ui| {
+                        changed |= ui
+                            .checkbox(
+                                &mut self.settings.large_amount_warning,
+                                "Ask before adding more than",
+                            )
+                            .changed();
+                        ui.add_enabled_ui(self.settings.large_amount_warning, |ui| {
+                            changed |= ui
+                                .add(
+                                    egui::DragValue::new(&mut self.settings.large_amount_threshold)
+                                        .clamp_range(1.0..=f64::MAX)
+                                        .speed(100.0),
+                                )
+                                .changed();
+                        });
+                    });
+                    ui.end_row();
+
+                    ui.label(egui::RichText::new("🚨 Exposure limits:").size(14.0));
+                    ui.horizontal(|ui| {
+                        changed |= ui
+                            .checkbox(&mut self.settings.exposure_warning, "Warn above")
+                            .changed();
+                        ui.add_enabled_ui(self.settings.exposure_warning, |ui| {
+                            changed |= ui
+                                .add(
+                                    egui::DragValue::new(&mut self.settings.exposure_limit_total)
+                                        .clamp_range(1.0..=f64::MAX)
+                                        .speed(100.0),
+                                )
+                                .changed();
+                            ui.label("lent out in total, or");
+                            changed |= ui
+                                .add(
+                                    egui::DragValue::new(&mut self.settings.exposure_limit_person)
+                                        .clamp_range(1.0..=f64::MAX)
+                                        .speed(100.0),
+                                )
+                                .changed();
+                            ui.label("per person");
+                        });
+                    });
+                    ui.end_row();
+
+                    ui.label(egui::RichText::new("💱 Currency order:").size(14.0));
+                    ui.horizontal_wrapped(|ui| {
+                        let mut currencies = self.settings.currencies();
+                        let mut move_up = None;
+                        for (idx, money_type) in currencies.iter().enumerate() {
+                            if ui
+                                .add_enabled(idx > 0, egui::Button::new("⏴").small())
+                                .clicked()
+                            {
+                                move_up = Some(idx);
+                            }
+                            ui.label(self.settings.currency_code(*money_type));
+                            if ui
+                                .add_enabled(
+                                    idx + 1 < currencies.len(),
+                                    egui::Button::new("⏵").small(),
+                                )
+                                .clicked()
+                            {
+                                move_up = Some(idx + 1);
+                            }
+                            ui.add_space(8.0);
+                        }
+                        if let Some(idx) = move_up {
+                            currencies.swap(idx - 1, idx);
+                            self.settings.currency_order = currencies;
+                            changed = true;
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label(egui::RichText::new("📊 Analysis sections:").size(14.0));
+                    ui.vertical(|ui| {
+                        let mut sections = self.settings.analysis_sections();
+                        let mut move_up = None;
+                        for (idx, (section, visible)) in sections.iter_mut().enumerate() {
+                            ui.horizontal(|ui| {
+                                if ui
+                                    .add_enabled(idx > 0, egui::Button::new("⏶").small())
+                                    .clicked()
+                                {
+                                    move_up = Some(idx);
+                                }
+                                if ui
+                                    .add_enabled(
+                                        idx + 1 < AnalysisSection::ALL.len(),
+                                        egui::Button::new("⏷").small(),
+                                    )
+                                    .clicked()
+                                {
+                                    move_up = Some(idx + 1);
+                                }
+                                changed |= ui.checkbox(visible, section.label()).changed();
                             });
                         }
+                        if let Some(idx) = move_up {
+                            sections.swap(idx - 1, idx);
+                            changed = true;
+                        }
+                        self.settings.analysis_layout = sections;
                     });
-                });
+                    ui.end_row();
 
-                ui.add_space(30.0);
+                    ui.label(egui::RichText::new("🗄 Storage:").size(14.0));
+                    ui.horizontal(|ui| {
+                        changed |= self.show_storage_choice(ui);
+                    });
+                    ui.end_row();
 
-                ui.horizontal(|ui| {
-                    ui.add_space(margin);
-                    ui.separator();
+                    ui.label(egui::RichText::new("📥 Import:").size(14.0));
+                    if ui.button("Import data file…").clicked() {
+                        self.pick_import_file();
+                    }
+                    ui.end_row();
+
+                    ui.label(egui::RichText::new("🩺 Integrity:").size(14.0));
+                    if ui
+                        .button("Check data…")
+                        .on_hover_text(
+                            "Look for over-returned loans, future dates and attachment problems",
+                        )
+                        .clicked()
+                    {
+                        self.integrity_report = Some(self.check_data());
+                    }
+                    ui.end_row();
+
+                    ui.label(egui::RichText::new("🧹 Start over:").size(14.0));
+                    if ui.button("Clear all transactions…").clicked() {
+                        self.clear_data_confirm = Some(String::new());
+                    }
+                    ui.end_row();
                 });
 
-                ui.add_space(20.0);
+            if changed {
+                if let Err(e) = self.settings.save() {
+                    self.status_message = format!("❌ Error saving settings: {}", e);
+                }
+            }
 
-                ui.vertical_centered(|ui| {
-                    ui.heading(
-                        egui::RichText::new("👥 Individual Statistics")
-                            .size(20.0)
-                            .strong(),
+            self.show_status_message(ui);
+        });
+    }
+
+    fn show_deadline_window(&mut self, ctx: &egui::Context) {
+        let Some(edit_idx) = self.editing_deadline_for else {
+            return;
+        };
+
+        let mut should_close = false;
+        let mut should_save = false;
+
+        egui::Window::new("📝 Change Deadline")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if let Some(t) = self.db.transactions.get(edit_idx) {
+                    full_name_on_hover(
+                        ui.label(format!(
+                            "Changing deadline for: {}",
+                            short_name(&t.person.name)
+                        )),
+                        &t.person.name,
                     );
-                });
+                    ui.label(format!("Transaction #{}", edit_idx + 1));
+                    ui.separator();
 
-                ui.add_space(15.0);
+                    ui.add_space(10.0);
 
-                ui.horizontal(|ui| {
-                    ui.add_space(margin);
-                    ui.vertical(|ui| {
-                        ui.set_width(content_width);
-                        ui.horizontal(|ui| {
-                            ui.label("🔍 Search person:");
-                            ui.add(
-                                egui::TextEdit::singleline(&mut self.search_query)
-                                    .desired_width(200.0),
-                            );
-                        });
-                    });
-                });
+                    if let Some(current_deadline) = t.expected_return_date {
+                        ui.label(format!(
+                            "Current deadline: {}",
+                            current_deadline.format("%Y-%m-%d")
+                        ));
 
-                ui.add_space(15.0);
+                        if !t.deadline_changes.is_empty() {
+                            ui.label(format!(
+                                "Previously changed {} time(s)",
+                                t.deadline_changes.len()
+                            ));
+                            ui.add_space(5.0);
 
-                let person_data = self.calculate_person_stats();
-                let mut people: Vec<_> = person_data.iter().collect();
-                people.sort_by(|a, b| {
-                    let a_reliability = if a.1.lent > 0.0 {
-                        a.1.returned / a.1.lent
-                    } else {
-                        0.0
-                    };
-                    let b_reliability = if b.1.lent > 0.0 {
-                        b.1.returned / b.1.lent
+                            let (later, earlier, net) = deadline_shift_summary(&t.deadline_changes);
+                            ui.colored_label(
+                                deadline_shift_color(net),
+                                format!(
+                                    "Net {:+} days ({} pushed later, {} moved earlier)",
+                                    net, later, earlier
+                                ),
+                            );
+
+                            egui::CollapsingHeader::new("📜 Change History").show(ui, |ui| {
+                                for (idx, change) in t.deadline_changes.iter().enumerate() {
+                                    let shift = (change.new_date - change.old_date).num_days();
+                                    ui.colored_label(
+                                        deadline_shift_color(shift),
+                                        format!(
+                                            "{}. {} ➡ {} ({:+} days, changed on {})",
+                                            idx + 1,
+                                            change.old_date.format("%Y-%m-%d"),
+                                            change.new_date.format("%Y-%m-%d"),
+                                            shift,
+                                            change
+                                                .changed_at
+                                                .with_timezone(&Local)
+                                                .format("%Y-%m-%d %H:%M")
+                                        ),
+                                    );
+                                }
+                            });
+                        }
+                    }
+
+                    ui.add_space(15.0);
+                    ui.label("New deadline:");
+                    ui.add(egui_extras::DatePickerButton::new(
+                        &mut self.temp_new_deadline,
+                    ));
+
+                    ui.add_space(15.0);
+                    if self.confirm_discard == Some(EditWindow::Deadline) {
+                        ui.colored_label(
+                            egui::Color32::from_rgb(255, 200, 100),
+                            "⚠ Discard changes?",
+                        );
+                        ui.horizontal(|ui| {
+                            if ui.button("🗑 Discard").clicked() {
+                                should_close = true;
+                            }
+                            if ui.button("↩ Keep editing").clicked() {
+                                self.confirm_discard = None;
+                            }
+                        });
                     } else {
-                        0.0
-                    };
-                    match b_reliability
-                        .partial_cmp(&a_reliability)
-                        .unwrap_or(std::cmp::Ordering::Equal)
+                        ui.horizontal(|ui| {
+                            if ui.button("💾 Save").clicked() {
+                                should_save = true;
+                                should_close = true;
+                            }
+                            if ui.button("❌ Cancel").clicked() {
+                                if t.expected_return_date
+                                    .is_some_and(|date| date != self.temp_new_deadline)
+                                {
+                                    self.confirm_discard = Some(EditWindow::Deadline);
+                                } else {
+                                    should_close = true;
+                                }
+                            }
+                        });
+                    }
+                }
+            });
+
+        if should_save {
+            if let Some(t) = self.db.transactions.get_mut(edit_idx) {
+                if t.expected_return_date.is_none() {
+                    t.expected_return_date = Some(self.temp_new_deadline);
+                    let description = format!(
+                        "Deadline for #{} ({}) set to {}",
+                        edit_idx + 1,
+                        t.person.name,
+                        self.temp_new_deadline.format("%Y-%m-%d")
+                    );
+                    self.db
+                        .record_audit(AuditAction::DeadlineChanged, description);
+                    self.persist();
+                    self.status_message = "✅ Deadline set!".to_string();
+                } else if let Some(old_deadline) = t.expected_return_date {
+                    if old_deadline != self.temp_new_deadline {
+                        let change = DeadlineChange {
+                            old_date: old_deadline,
+                            new_date: self.temp_new_deadline,
+                            changed_at: Utc::now(),
+                        };
+                        t.deadline_changes.push(change);
+                        t.expected_return_date = Some(self.temp_new_deadline);
+
+                        let description = format!(
+                            "Deadline for #{} ({}) moved {} ➡ {}",
+                            edit_idx + 1,
+                            t.person.name,
+                            old_deadline.format("%Y-%m-%d"),
+                            self.temp_new_deadline.format("%Y-%m-%d")
+                        );
+                        self.db
+                            .record_audit(AuditAction::DeadlineChanged, description);
+
+                        self.persist();
+                        self.status_message = "✅ Deadline updated!".to_string();
+                    }
+                }
+            }
+        }
+
+        if should_close {
+            self.editing_deadline_for = None;
+            self.confirm_discard = None;
+        }
+    }
+
+    fn apply_edit_draft(&mut self, index: usize, draft: &EditDraft) -> Result<(), String> {
+        let name = draft.person_name.trim();
+        if name.is_empty() {
+            return Err("Enter a person".to_string());
+        }
+        let amount = parse_direction_amount(draft.direction, &draft.amount)
+            .map_err(|e| format!("Amount {}", e))?;
+        let Some(mut updated) = self.db.transactions.get(index).cloned() else {
+            return Ok(());
+        };
+
+        if updated.person.name != name {
+            updated.person = self.db.person_contact(name);
+        }
+        updated.amount = amount;
+        updated.money_type = draft.money_type;
+        updated.direction = draft.direction;
+        // Only a changed date or time replaces the stored one, which keeps its seconds.
+        let local = updated.local_datetime();
+        if (local.date(), local.hour(), local.minute()) != (draft.date, draft.hour, draft.minute) {
+            let time = NaiveTime::from_hms_opt(draft.hour, draft.minute, 0)
+                .ok_or_else(|| format!("Invalid time {:02}:{:02}", draft.hour, draft.minute))?;
+            updated.datetime = local_to_utc(NaiveDateTime::new(draft.date, time));
+        }
+        updated.tags = Vec::new();
+        for tag in draft.tags.split_whitespace() {
+            let tag = tag.trim_start_matches('#');
+            if !tag.is_empty() && !updated.tags.iter().any(|existing| existing == tag) {
+                updated.tags.push(tag.to_string());
+            }
+        }
+        updated.note = draft.note.trim().to_string();
+        updated.attachment_path = draft.attachment_path.clone();
+        self.db.update_transaction(index, updated);
+        Ok(())
+    }
+
+    /// Deletes an attachment copied in for the draft but never saved.
+    fn discard_staged_attachment(&self, draft: &EditDraft, original: &EditDraft) {
+        if let Some(path) = &draft.attachment_path {
+            if original.attachment_path.as_ref() != Some(path) {
+                self.db.remove_unreferenced_attachment(path);
+            }
+        }
+    }
+
+    fn show_reminder_window(&mut self, ctx: &egui::Context) {
+        let Some(edit_idx) = self.editing_reminder_for else {
+            return;
+        };
+
+        let mut should_close = false;
+        let mut should_save = false;
+
+        egui::Window::new("🔔 Reminder")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if let Some(t) = self.db.transactions.get(edit_idx) {
+                    full_name_on_hover(
+                        ui.label(format!("Reminder for: {}", short_name(&t.person.name))),
+                        &t.person.name,
+                    );
+                    ui.label(format!("Transaction #{}", edit_idx + 1));
+                    ui.separator();
+
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.temp_reminder)
+                            .hint_text("e.g. ask at the office party")
+                            .desired_width(300.0),
+                    );
+
+                    ui.add_space(15.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("💾 Save").clicked() {
+                            should_save = true;
+                            should_close = true;
+                        }
+                        if ui.button("❌ Cancel").clicked() {
+                            should_close = true;
+                        }
+                    });
+                }
+            });
+
+        if should_save {
+            if let Some(t) = self.db.transactions.get_mut(edit_idx) {
+                let reminder =
+                    Some(self.temp_reminder.trim().to_string()).filter(|r| !r.is_empty());
+                if t.reminder != reminder {
+                    let description = format!(
+                        "Reminder of #{} ({}) {}",
+                        edit_idx + 1,
+                        t.person.name,
+                        if reminder.is_some() { "set" } else { "removed" }
+                    );
+                    t.reminder = reminder;
+                    self.db.record_audit(AuditAction::Edited, description);
+                    self.persist();
+                    self.status_message = "✅ Reminder saved!".to_string();
+                }
+            }
+        }
+
+        if should_close {
+            self.editing_reminder_for = None;
+        }
+    }
+
+    fn check_data(&self) -> IntegrityReport {
+        let person_data = self.calculate_person_stats();
+        let mut names: Vec<&String> = person_data.keys().collect();
+        names.sort();
+        let mut over_returns = Vec::new();
+        for name in names {
+            for money_type in self.settings.currencies() {
+                let Some(c) = person_data[name].by_currency.get(&money_type) else {
+                    continue;
+                };
+                for (label, loaned, excess) in [
+                    ("returned", c.lent, c.returned - c.lent),
+                    ("repaid", c.borrowed, c.repaid - c.borrowed),
+                ] {
+                    if excess > 0.0 && !self.settings.is_negligible(loaned, excess) {
+                        over_returns.push((name.clone(), money_type, label, excess));
+                    }
+                }
+            }
+        }
+
+        let now = Utc::now();
+        IntegrityReport {
+            over_returns,
+            future_dated: self
+                .db
+                .transactions
+                .iter()
+                .filter(|t| t.datetime > now)
+                .map(|t| t.id)
+                .collect(),
+            missing_attachments: self
+                .db
+                .transactions
+                .iter()
+                .filter(|t| {
+                    t.attachment_path
+                        .as_ref()
+                        .is_some_and(|path| !Path::new(path).exists())
+                })
+                .map(|t| t.id)
+                .collect(),
+            orphaned_files: self.db.orphaned_attachments(&self.db.archive()),
+        }
+    }
+
+    fn show_integrity_window(&mut self, ctx: &egui::Context) {
+        let Some(report) = &self.integrity_report else {
+            return;
+        };
+
+        let mut open = true;
+        let mut open_person = None;
+        let mut move_to_now = None;
+        let mut clear_link = None;
+        let mut delete_files = Vec::new();
+
+        egui::Window::new("🩺 Data Check")
+            .collapsible(false)
+            .resizable(true)
+            .default_width(520.0)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                if report.is_empty() {
+                    ui.colored_label(
+                        egui::Color32::from_rgb(130, 220, 130),
+                        "✅ No problems found",
+                    );
+                    return;
+                }
+
+                egui::ScrollArea::vertical()
+                    .max_height(450.0)
+                    .show(ui, |ui| {
+                        if !report.over_returns.is_empty() {
+                            ui.heading(
+                                egui::RichText::new(format!(
+                                    "↩ More paid back than lent ({})",
+                                    report.over_returns.len()
+                                ))
+                                .size(16.0),
+                            );
+                            for (name, money_type, label, excess) in &report.over_returns {
+                                ui.horizontal(|ui| {
+                                    full_name_on_hover(
+                                        ui.label(format!(
+                                            "{}: {} more {} than went out",
+                                            short_name(name),
+                                            self.settings.format_money_code(*money_type, *excess),
+                                            label
+                                        )),
+                                        name,
+                                    );
+                                    if ui.small_button("👤 Open").clicked() {
+                                        open_person = Some(name.clone());
+                                    }
+                                });
+                            }
+                            ui.add_space(10.0);
+                        }
+
+                        if !report.future_dated.is_empty() {
+                            ui.heading(
+                                egui::RichText::new(format!(
+                                    "🔮 Dated in the future ({})",
+                                    report.future_dated.len()
+                                ))
+                                .size(16.0),
+                            );
+                            for id in &report.future_dated {
+                                let Some((idx, t)) = self.db.find_by_id(*id) else {
+                                    continue;
+                                };
+                                ui.horizontal(|ui| {
+                                    ui.label(format!(
+                                        "#{} {} {:?} {} — {}",
+                                        idx + 1,
+                                        t.local_datetime().format("%Y-%m-%d %H:%M"),
+                                        t.direction,
+                                        self.settings.format_money(t.money_type, t.amount),
+                                        short_name(&t.person.name)
+                                    ));
+                                    if ui.small_button("🕐 Set to now").clicked() {
+                                        move_to_now = Some(idx);
+                                    }
+                                });
+                            }
+                            ui.add_space(10.0);
+                        }
+
+                        if !report.missing_attachments.is_empty() {
+                            ui.heading(
+                                egui::RichText::new(format!(
+                                    "📎 Attachment file missing ({})",
+                                    report.missing_attachments.len()
+                                ))
+                                .size(16.0),
+                            );
+                            for id in &report.missing_attachments {
+                                let Some((idx, t)) = self.db.find_by_id(*id) else {
+                                    continue;
+                                };
+                                ui.horizontal(|ui| {
+                                    ui.label(format!(
+                                        "#{} {} — {}",
+                                        idx + 1,
+                                        short_name(&t.person.name),
+                                        t.attachment_path.as_deref().unwrap_or_default()
+                                    ));
+                                    if ui
+                                        .small_button("Clear")
+                                        .on_hover_text("Remove the link to the missing file")
+                                        .clicked()
+                                    {
+                                        clear_link = Some(idx);
+                                    }
+                                });
+                            }
+                            ui.add_space(10.0);
+                        }
+
+                        if !report.orphaned_files.is_empty() {
+                            ui.horizontal(|ui| {
+                                ui.heading(
+                                    egui::RichText::new(format!(
+                                        "🗃 Files no transaction uses ({})",
+                                        report.orphaned_files.len()
+                                    ))
+                                    .size(16.0),
+                                );
+                                if ui.small_button("🗑 Delete all").clicked() {
+                                    delete_files = report.orphaned_files.clone();
+                                }
+                            });
+                            for path in &report.orphaned_files {
+                                ui.horizontal(|ui| {
+                                    ui.label(path.display().to_string());
+                                    if ui.small_button("🗑").on_hover_text("Delete file").clicked()
+                                    {
+                                        delete_files.push(path.clone());
+                                    }
+                                });
+                            }
+                        }
+                    });
+            });
+
+        let fixed = move_to_now.is_some() || clear_link.is_some() || !delete_files.is_empty();
+        if let Some(idx) = move_to_now {
+            if let Some(t) = self.db.transactions.get_mut(idx) {
+                t.datetime = Utc::now();
+                let description =
+                    format!("#{} ({}) moved to the current time", idx + 1, t.person.name);
+                self.db.record_audit(AuditAction::Edited, description);
+                self.persist();
+            }
+        }
+        if let Some(idx) = clear_link {
+            if let Some(path) = self
+                .db
+                .transactions
+                .get(idx)
+                .and_then(|t| t.attachment_path.clone())
+            {
+                self.db.set_attachment(idx, None);
+                self.missing_attachments.remove(&path);
+                self.persist();
+            }
+        }
+        for path in &delete_files {
+            if let Err(e) = std::fs::remove_file(path) {
+                self.status_message = format!("❌ Failed to delete {}: {}", path.display(), e);
+            }
+        }
+
+        if fixed {
+            self.integrity_report = Some(self.check_data());
+        }
+        if !open {
+            self.integrity_report = None;
+        }
+        if let Some(name) = open_person {
+            self.open_person_detail(&name);
+        }
+    }
+
+    fn show_clear_data_window(&mut self, ctx: &egui::Context) {
+        const CONFIRM_WORD: &str = "DELETE";
+        let Some(mut typed) = self.clear_data_confirm.take() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut clear = false;
+        let mut cancel = false;
+        egui::Window::new("⚠️ Clear all data")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "This removes all {} transaction(s). A backup bundle with the data file, \
+                     the archive and all attachments is written to the backups folder first.",
+                    self.db.transactions.len()
+                ));
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    ui.label(format!("Type {} to confirm:", CONFIRM_WORD));
+                    ui.text_edit_singleline(&mut typed);
+                });
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(
+                            typed.trim() == CONFIRM_WORD,
+                            egui::Button::new("🗑 Clear everything"),
+                        )
+                        .clicked()
                     {
-                        std::cmp::Ordering::Equal => a.0.cmp(b.0),
-                        other => other,
+                        clear = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
                     }
                 });
+            });
+
+        if !clear {
+            if open && !cancel {
+                self.clear_data_confirm = Some(typed);
+            }
+            return;
+        }
+
+        match self.db.clear_all() {
+            Ok(bundle) => {
+                self.selected_transactions.clear();
+                self.edit_transaction_index = None;
+                self.editing_deadline_for = None;
+                self.editing_reminder_for = None;
+                self.missing_attachments.clear();
+                self.persist();
+                self.status_message = format!(
+                    "✅ Cleared all transactions. Backup saved in {}",
+                    bundle.display()
+                );
+            }
+            Err(e) => {
+                self.status_message =
+                    format!("❌ Nothing was cleared because the backup failed: {}", e);
+            }
+        }
+    }
+
+    /// Picking another backend moves the data there; returns whether the setting changed.
+    fn show_storage_choice(&mut self, ui: &mut egui::Ui) -> bool {
+        let current = storage::backend();
+        let mut selected = current;
+        egui::ComboBox::from_id_source("storage_backend")
+            .selected_text(current.label())
+            .show_ui(ui, |ui| {
+                for backend in StorageBackend::ALL {
+                    ui.add_enabled_ui(backend.is_available(), |ui| {
+                        ui.selectable_value(&mut selected, backend, backend.label())
+                            .on_disabled_hover_text("build with --features sqlite for SQLite");
+                    });
+                }
+            })
+            .response
+            .on_hover_text("SQLite is faster for large histories. Switching moves the data and keeps the old file in backups.");
+        ui.label(egui::RichText::new(storage::active().path()).weak());
+
+        if selected == current || self.saver.is_busy() {
+            return false;
+        }
+        match self.db.migrate_to(selected) {
+            Ok(()) => {
+                self.settings.storage_backend = selected;
+                self.status_message = format!("✅ Data moved to {}", selected.label());
+                true
+            }
+            Err(e) => {
+                self.status_message =
+                    format!("❌ Error moving data to {}: {}", selected.label(), e);
+                false
+            }
+        }
+    }
+
+    fn pick_import_file(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Data file", &["json"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        match Database::read_import(&path) {
+            Some(transactions) if transactions.is_empty() => {
+                self.status_message = "⚠️ That file has no transactions".to_string();
+            }
+            Some(transactions) => {
+                self.pending_import = Some(transactions);
+                self.confirm_import_replace = false;
+            }
+            None => {
+                self.status_message = format!("❌ Could not read {}", path.display());
+            }
+        }
+    }
+
+    fn show_import_window(&mut self, ctx: &egui::Context) {
+        let Some(imported) = &self.pending_import else {
+            return;
+        };
+        let imported_count = imported.len();
+        let existing_count = self.db.transactions.len();
+        let mut choice = None;
+        let mut cancel = false;
+
+        egui::Window::new("📥 Import")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "The file contains {} transaction(s).",
+                    imported_count
+                ));
+                ui.add_space(10.0);
+
+                if self.confirm_import_replace {
+                    ui.colored_label(
+                        egui::Color32::from_rgb(255, 200, 100),
+                        format!(
+                            "⚠️ Replacing removes all {} existing transaction(s). \
+                             A backup is written to the backups folder first.",
+                            existing_count
+                        ),
+                    );
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("🗑 Replace my data").clicked() {
+                            choice = Some(true);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.confirm_import_replace = false;
+                        }
+                    });
+                } else {
+                    ui.horizontal(|ui| {
+                        if ui.button("➕ Add to my data").clicked() {
+                            choice = Some(false);
+                        }
+                        if ui.button("Replace my data…").clicked() {
+                            self.confirm_import_replace = true;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            cancel = true;
+                        }
+                    });
+                }
+            });
+
+        if cancel {
+            self.pending_import = None;
+        }
+
+        let Some(replace) = choice else {
+            return;
+        };
+        let imported = self.pending_import.take().unwrap_or_default();
+        self.confirm_import_replace = false;
+
+        match self.db.import_transactions(imported, replace) {
+            Ok(count) => {
+                self.selected_transactions.clear();
+                self.edit_transaction_index = None;
+                self.editing_deadline_for = None;
+                self.editing_reminder_for = None;
+                self.persist();
+                self.status_message = format!("✅ Imported {} transaction(s)", count);
+            }
+            Err(e) => {
+                self.status_message = format!("❌ Import cancelled, could not back up: {}", e);
+            }
+        }
+    }
+
+    fn show_status_message(&self, ui: &mut egui::Ui) {
+        if !self.status_message.is_empty() {
+            ui.add_space(15.0);
+            let color = if self.status_message.starts_with("✅") {
+                egui::Color32::GREEN
+            } else if self.status_message.starts_with("❌") {
+                egui::Color32::RED
+            } else {
+                egui::Color32::YELLOW
+            };
+            ui.colored_label(color, egui::RichText::new(&self.status_message).size(14.0));
+        }
+    }
+
+    fn show_add_transaction(&mut self, ui: &mut egui::Ui) {
+        ui.vertical_centered(|ui| {
+            ui.add_space(20.0);
+            ui.heading(
+                egui::RichText::new("Add New Transaction")
+                    .size(24.0)
+                    .strong(),
+            );
+            ui.add_space(20.0);
+
+            if let Some(loan_id) = self.form_linked_to {
+                self.show_linked_loan(ui, loan_id);
+                ui.add_space(10.0);
+            }
+
+            // Fields in keyboard order; Tab and Shift+Tab walk this instead of egui's default.
+            let mut focus_chain: Vec<egui::Response> = Vec::new();
+
+            let max_width = 500.0;
+            ui.allocate_ui_with_layout(
+                egui::vec2(max_width, ui.available_height()),
+                egui::Layout::top_down_justified(egui::Align::Center),
+                |ui| {
+                    egui::Grid::new("transaction_form")
+                        .num_columns(2)
+                        .spacing([40.0, 15.0])
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.label(egui::RichText::new("➗ Split:").size(14.0));
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut self.split_mode, "Split among people");
+                                if self.split_mode {
+                                    ui.radio_value(&mut self.split_equal, true, "Equal");
+                                    ui.radio_value(&mut self.split_equal, false, "Custom");
+                                }
+                            });
+                            ui.end_row();
+
+                            if self.split_mode {
+                                ui.label(egui::RichText::new("👥 People:").size(14.0));
+                                ui.vertical(|ui| {
+                                    let mut remove = None;
+                                    let can_remove = self.split_people.len() > 1;
+                                    for (idx, share) in self.split_people.iter_mut().enumerate() {
+                                        ui.horizontal(|ui| {
+                                            focus_chain.push(
+                                                ui.add(
+                                                    egui::TextEdit::singleline(&mut share.name)
+                                                        .hint_text("Name")
+                                                        .desired_width(160.0),
+                                                ),
+                                            );
+                                            if !self.split_equal {
+                                                focus_chain.push(
+                                                    ui.add(
+                                                        egui::TextEdit::singleline(
+                                                            &mut share.share,
+                                                        )
+                                                        .hint_text("Share")
+                                                        .desired_width(70.0),
+                                                    ),
+                                                );
+                                            }
+                                            if can_remove && ui.small_button("❌").clicked() {
+                                                remove = Some(idx);
+                                            }
+                                        });
+                                    }
+                                    if let Some(idx) = remove {
+                                        self.split_people.remove(idx);
+                                    }
+                                    if ui.small_button("➕ Add person").clicked() {
+                                        self.split_people.push(SplitShare::default());
+                                    }
+                                });
+                                ui.end_row();
+                            } else {
+                                let recent = self.recent_people(5);
+                                if !recent.is_empty() {
+                                    ui.label(egui::RichText::new("🕘 Recent:").size(14.0));
+                                    ui.horizontal_wrapped(|ui| {
+                                        for name in recent {
+                                            if full_name_on_hover(
+                                                ui.small_button(short_name(&name)),
+                                                &name,
+                                            )
+                                            .clicked()
+                                            {
+                                                self.person_name = name;
+                                            }
+                                        }
+                                    });
+                                    ui.end_row();
+                                }
+
+                                ui.label(egui::RichText::new("👤 Person:").size(14.0));
+                                focus_chain.push(ui.text_edit_singleline(&mut self.person_name));
+                                ui.end_row();
+                            }
+
+                            ui.label(egui::RichText::new("💵 Amount:").size(14.0));
+                            ui.horizontal(|ui| {
+                                focus_chain.push(ui.text_edit_singleline(&mut self.amount));
+                                match self.form_amount() {
+                                    Ok(_) => {
+                                        ui.colored_label(egui::Color32::GREEN, "✔");
+                                    }
+                                    Err(_) if self.amount.trim().is_empty() => {}
+                                    Err(hint) => {
+                                        ui.colored_label(egui::Color32::RED, hint);
+                                    }
+                                }
+                            });
+                            ui.end_row();
+
+                            ui.label(egui::RichText::new("💱 Currency:").size(14.0));
+                            let currency = egui::ComboBox::from_id_source("money_type")
+                                .selected_text(self.settings.currency_code(self.money_type))
+                                .show_ui(ui, |ui| {
+                                    for option in self.settings.currencies() {
+                                        ui.selectable_value(
+                                            &mut self.money_type,
+                                            option,
+                                            self.settings.currency_code(option),
+                                        );
+                                    }
+                                })
+                                .response;
+                            step_with_arrows(
+                                ui,
+                                &currency,
+                                &self.settings.currencies(),
+                                &mut self.money_type,
+                            );
+                            focus_chain.push(currency);
+                            ui.end_row();
+
+                            if !self.split_mode {
+                                ui.label(egui::RichText::new("➕ Also in:").size(14.0));
+                                ui.vertical(|ui| {
+                                    let mut remove = None;
+                                    for (idx, (amount, money_type)) in
+                                        self.extra_amounts.iter_mut().enumerate()
+                                    {
+                                        ui.horizontal(|ui| {
+                                            focus_chain.push(
+                                                ui.add(
+                                                    egui::TextEdit::singleline(amount)
+                                                        .hint_text("Amount")
+                                                        .desired_width(100.0),
+                                                ),
+                                            );
+                                            let currency = egui::ComboBox::from_id_source((
+                                                "extra_money_type",
+                                                idx,
+                                            ))
+                                            .selected_text(self.settings.currency_code(*money_type))
+                                            .show_ui(ui, |ui| {
+                                                for option in self.settings.currencies() {
+                                                    ui.selectable_value(
+                                                        money_type,
+                                                        option,
+                                                        self.settings.currency_code(option),
+                                                    );
+                                                }
+                                            })
+                                            .response;
+                                            step_with_arrows(
+                                                ui,
+                                                &currency,
+                                                &self.settings.currencies(),
+                                                money_type,
+                                            );
+                                            focus_chain.push(currency);
+                                            if ui.small_button("❌").clicked() {
+                                                remove = Some(idx);
+                                            }
+                                        });
+                                    }
+                                    if let Some(idx) = remove {
+                                        self.extra_amounts.remove(idx);
+                                    }
+                                    if ui
+                                        .small_button("➕ Another currency")
+                                        .on_hover_text(
+                                            "Record part of this in a different currency; \
+                                             the parts are kept together as one entry",
+                                        )
+                                        .clicked()
+                                    {
+                                        let next = self
+                                            .settings
+                                            .currencies()
+                                            .into_iter()
+                                            .find(|money_type| {
+                                                *money_type != self.money_type
+                                                    && !self
+                                                        .extra_amounts
+                                                        .iter()
+                                                        .any(|(_, used)| used == money_type)
+                                            })
+                                            .unwrap_or(self.money_type);
+                                        self.extra_amounts.push((String::new(), next));
+                                    }
+                                });
+                                ui.end_row();
+                            }
+
+                            ui.label(egui::RichText::new("🔄 Direction:").size(14.0));
+                            if self.split_mode {
+                                ui.label("Lent (I gave)");
+                                self.direction = Direction::Lent;
+                            } else {
+                                let direction = egui::ComboBox::from_id_source("direction")
+                                    .selected_text(format!("{:?}", self.direction))
+                                    .show_ui(ui, |ui| {
+                                        ui.selectable_value(
+                                            &mut self.direction,
+                                            Direction::Lent,
+                                            "Lent (I gave)",
+                                        );
+                                        ui.selectable_value(
+                                            &mut self.direction,
+                                            Direction::Borrowed,
+                                            "Borrowed (I received)",
+                                        );
+                                        ui.selectable_value(
+                                            &mut self.direction,
+                                            Direction::Returned,
+                                            "Returned (They gave back)",
+                                        );
+                                        ui.selectable_value(
+                                            &mut self.direction,
+                                            Direction::Repaid,
+                                            "Repaid (I gave back)",
+                                        );
+                                        ui.selectable_value(
+                                            &mut self.direction,
+                                            Direction::Gift,
+                                            "Gift (not a loan)",
+                                        );
+                                        ui.selectable_value(
+                                            &mut self.direction,
+                                            Direction::Adjustment,
+                                            "Adjustment (correct a balance, ± amount)",
+                                        );
+                                    })
+                                    .response;
+                                step_with_arrows(
+                                    ui,
+                                    &direction,
+                                    &Direction::ALL,
+                                    &mut self.direction,
+                                );
+                                focus_chain.push(direction);
+                            }
+                            ui.end_row();
+
+                            ui.label(egui::RichText::new("📅 Date:").size(14.0));
+                            focus_chain.push(
+                                ui.add(egui_extras::DatePickerButton::new(&mut self.selected_date)),
+                            );
+                            ui.end_row();
+
+                            ui.label(egui::RichText::new("⏰ Time:").size(14.0));
+                            ui.horizontal(|ui| {
+                                let two_digits = |n: f64, _| format!("{:02}", n);
+                                let mut hour = self.selected_hour as i32;
+                                let mut minute = self.selected_minute as i32;
+                                let hour_response = ui.add(
+                                    egui::DragValue::new(&mut hour)
+                                        .clamp_range(-1..=24)
+                                        .custom_formatter(two_digits),
+                                );
+                                ui.label(":");
+                                let minute_response = ui.add(
+                                    egui::DragValue::new(&mut minute)
+                                        .clamp_range(-1..=60)
+                                        .custom_formatter(two_digits),
+                                );
+                                let changed = hour_response.changed() || minute_response.changed();
+                                focus_chain.push(hour_response);
+                                focus_chain.push(minute_response);
+                                if changed {
+                                    // Going past either end rolls over instead of clamping.
+                                    let total = (hour * 60 + minute).rem_euclid(24 * 60);
+                                    self.selected_hour = (total / 60) as u32;
+                                    self.selected_minute = (total % 60) as u32;
+                                }
+                                if ui
+                                    .small_button("Now")
+                                    .on_hover_text(
+                                        "Set the date and time to the current local time",
+                                    )
+                                    .clicked()
+                                {
+                                    let now = Local::now();
+                                    self.selected_date = now.date_naive();
+                                    self.selected_hour = now.hour();
+                                    self.selected_minute = now.minute();
+                                }
+                            });
+                            ui.end_row();
+
+                            ui.label(egui::RichText::new("📖 Expected Return:").size(14.0));
+                            ui.horizontal(|ui| {
+                                let can_set_expected =
+                                    matches!(self.direction, Direction::Lent | Direction::Borrowed);
+
+                                if can_set_expected {
+                                    focus_chain.push(
+                                        ui.checkbox(&mut self.has_expected_return, "Set date"),
+                                    );
+                                    if self.has_expected_return {
+                                        focus_chain.push(ui.add(
+                                            egui_extras::DatePickerButton::new(
+                                                &mut self.expected_return_date,
+                                            ),
+                                        ));
+                                        for (label, months, days) in [
+                                            ("+1 week", 0, 7),
+                                            ("+1 month", 1, 0),
+                                            ("+3 months", 3, 0),
+                                        ] {
+                                            if ui.small_button(label).clicked() {
+                                                self.expected_return_date = (self.selected_date
+                                                    + chrono::Months::new(months))
+                                                    + chrono::Duration::days(days);
+                                            }
+                                        }
+                                    }
+                                } else {
+                                    ui.label(egui::RichText::new("(N/A)").weak());
+                                    self.has_expected_return = false;
+                                }
+                            });
+                            ui.end_row();
+
+                            ui.label(egui::RichText::new("📝 Note:").size(14.0));
+                            focus_chain.push(ui.add(
+                                egui::TextEdit::singleline(&mut self.note).hint_text("optional"),
+                            ));
+                            ui.end_row();
+
+                            ui.label(egui::RichText::new("📎 Attachment:").size(14.0));
+                            ui.horizontal(|ui| {
+                                let browse = ui.button("📁 Browse...");
+                                let browse_clicked = browse.clicked();
+                                focus_chain.push(browse);
+                                if browse_clicked {
+                                    if let Some(path) = rfd::FileDialog::new()
+                                        .add_filter("Images & PDFs", ATTACHMENT_EXTENSIONS)
+                                        .pick_file()
+                                    {
+                                        self.start_attachment_copy(
+                                            path.to_string_lossy().to_string(),
+                                        );
+                                    }
+                                }
+
+                                if let Some((ref source, _)) = self.attachment_copy {
+                                    ui.spinner();
+                                    ui.label(
+                                        egui::RichText::new(format!(
+                                            "Copying {}…",
+                                            PathBuf::from(source)
+                                                .file_name()
+                                                .and_then(|n| n.to_str())
+                                                .unwrap_or("file")
+                                        ))
+                                        .weak(),
+                                    );
+                                } else if let Some(ref path) = self.attachment_path {
+                                    ui.label(
+                                        PathBuf::from(path)
+                                            .file_name()
+                                            .and_then(|n| n.to_str())
+                                            .unwrap_or("file"),
+                                    );
+                                    if ui.small_button("❌").clicked() {
+                                        self.discard_form_attachment();
+                                    }
+                                } else {
+                                    ui.label(egui::RichText::new("None").weak());
+                                }
+                            });
+                            ui.end_row();
+                        });
+                },
+            );
+
+            // Enter in a text field ends editing there; treat it as submitting the form.
+            let submit = ui.input(|i| i.key_pressed(egui::Key::Enter))
+                && focus_chain.iter().any(|response| response.lost_focus());
+            cycle_focus(ui, &focus_chain);
+
+            ui.add_space(20.0);
+
+            let form_ready = self.form_amount().is_ok()
+                && (self.split_mode
+                    || (!self.person_name.trim().is_empty() && self.form_extra_amounts().is_ok()));
+            if form_ready {
+                ui.label(egui::RichText::new(format!("👁 {}", self.form_preview())).italics());
+                ui.add_space(8.0);
+            }
+            let clicked = ui
+                .add_enabled(
+                    self.attachment_copy.is_none() && form_ready,
+                    egui::Button::new(
+                        egui::RichText::new("✅ Add Transaction")
+                            .size(16.0)
+                            .strong()
+                            .color(egui::Color32::WHITE),
+                    )
+                    .fill(egui::Color32::from_rgb(100, 200, 100))
+                    .min_size([150.0, 45.0].into()),
+                )
+                .on_disabled_hover_text("Enter a person and a positive amount first")
+                .clicked();
+            if clicked || (submit && form_ready && self.attachment_copy.is_none()) {
+                self.add_from_form(false);
+            }
+
+            if self.confirm_large_amount {
+                self.show_large_amount_confirm(ui.ctx());
+            }
+
+            self.show_status_message(ui);
+        });
+    }
+
+    fn open_attachment(&mut self, ctx: &egui::Context, path: &str) {
+        if !Path::new(path).exists() {
+            self.missing_attachments.insert(path.to_string());
+            self.status_message = format!("⚠️ Attachment not found: {}", path);
+            return;
+        }
+
+        if !is_pdf(path) && !self.attachment_textures.contains_key(path) {
+            if let Ok(img) = image::open(path) {
+                let img = img.to_rgba8();
+                let (w, h) = img.dimensions();
+                let pixels = img.into_raw();
+                let color_img =
+                    egui::ColorImage::from_rgba_premultiplied([w as usize, h as usize], &pixels);
+                let texture = ctx.load_texture(path, color_img, egui::TextureOptions::LINEAR);
+                self.attachment_textures.insert(path.to_string(), texture);
+            }
+        }
+        self.viewing_attachment = Some(path.to_string());
+    }
+
+    fn poll_thumbnails(&mut self, ctx: &egui::Context) {
+        while let Ok((path, image)) = self.thumbnail_receiver.try_recv() {
+            let thumbnail = match image {
+                Some(image) => Thumbnail::Ready(ctx.load_texture(
+                    format!("thumb:{}", path),
+                    image,
+                    egui::TextureOptions::LINEAR,
+                )),
+                None => Thumbnail::Failed,
+            };
+            self.thumbnails.insert(path, thumbnail);
+        }
+    }
+
+    /// Hands the file to the OS default application. The launcher is awaited on a
+    /// background thread so a missing handler can be reported without blocking.
+    fn open_externally(&mut self, path: &str) {
+        let mut command = open_command(path);
+        let sender = self.open_error_sender.clone();
+        let name = path.to_string();
+        std::thread::spawn(move || {
+            let error = match command.status() {
+                // Explorer exits with 1 even after opening the file.
+                Ok(status) if status.success() || cfg!(target_os = "windows") => return,
+                Ok(status) => format!("no application could open {} ({})", name, status),
+                Err(e) => e.to_string(),
+            };
+            let _ = sender.send(error);
+        });
+    }
+
+    fn poll_open_errors(&mut self) {
+        while let Ok(e) = self.open_error_receiver.try_recv() {
+            self.status_message = format!("❌ Could not open attachment: {}", e);
+        }
+    }
+
+    fn start_attachment_copy(&mut self, source: String) {
+        let (sender, receiver) = mpsc::channel();
+        let source_path = source.clone();
+        std::thread::spawn(move || {
+            let result =
+                Database::copy_attachment_to_storage(&source_path).map_err(|e| e.to_string());
+            let _ = sender.send(result);
+        });
+        self.discard_form_attachment();
+        self.attachment_copy = Some((source, receiver));
+    }
+
+    fn discard_form_attachment(&mut self) {
+        if let Some(path) = self.attachment_path.take() {
+            self.db.remove_unreferenced_attachment(&path);
+        }
+    }
+
+    fn poll_attachment_copy(&mut self, ctx: &egui::Context) {
+        let Some((_, receiver)) = &self.attachment_copy else {
+            return;
+        };
+
+        match receiver.try_recv() {
+            Ok(Ok(stored_path)) => {
+                self.attachment_path = Some(stored_path);
+                self.attachment_copy = None;
+            }
+            Ok(Err(e)) => {
+                self.status_message = format!("⚠️ Failed to copy attachment: {}", e);
+                self.attachment_copy = None;
+            }
+            Err(mpsc::TryRecvError::Empty) => {
+                ctx.request_repaint_after(std::time::Duration::from_millis(100));
+            }
+            Err(mpsc::TryRecvError::Disconnected) => {
+                self.status_message = "⚠️ Failed to copy attachment".to_string();
+                self.attachment_copy = None;
+            }
+        }
+    }
+
+    fn show_linked_loan(&mut self, ui: &mut egui::Ui, loan_id: u64) {
+        let Some((idx, loan)) = self.db.find_by_id(loan_id) else {
+            ui.horizontal(|ui| {
+                ui.label("🔗 Linked loan no longer exists");
+                if ui.small_button("❌").on_hover_text("Unlink").clicked() {
+                    self.form_linked_to = None;
+                }
+            });
+            return;
+        };
+
+        let outstanding = loan.amount
+            - self
+                .calculate_settled_amounts()
+                .get(&idx)
+                .copied()
+                .unwrap_or(0.0);
+        let money_type = loan.money_type;
+
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "🔗 Recording against #{}: {:?} {} ({})",
+                idx + 1,
+                loan.direction,
+                self.settings.format_money(money_type, loan.amount),
+                loan.local_datetime().format("%Y-%m-%d")
+            ));
+            if ui.small_button("❌").on_hover_text("Unlink").clicked() {
+                self.form_linked_to = None;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "Outstanding: {}",
+                self.settings.format_money(money_type, outstanding)
+            ));
+            if let Ok(amount) = parse_amount(&self.amount) {
+                let remaining = outstanding - amount;
+                ui.label("→");
+                if remaining < -0.005 {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!(
+                            "⚠️ {} more than what is left",
+                            self.settings.format_money(money_type, -remaining)
+                        ),
+                    );
+                } else {
+                    ui.label(format!(
+                        "remaining after this: {}",
+                        self.settings.format_money(money_type, remaining.max(0.0))
+                    ));
+                }
+            }
+        });
+    }
+
+    fn prefill_return(&mut self, loan_index: usize, remaining: f64) {
+        let Some(loan) = self.db.transactions.get(loan_index) else {
+            return;
+        };
+
+        let now = Local::now();
+        self.person_name = loan.person.name.clone();
+        self.amount = format!("{:.*}", self.settings.decimals(loan.money_type), remaining);
+        self.money_type = loan.money_type;
+        self.direction = if loan.direction == Direction::Lent {
+            Direction::Returned
+        } else {
+            Direction::Repaid
+        };
+        self.selected_date = now.date_naive();
+        self.selected_hour = now.hour();
+        self.selected_minute = now.minute();
+        self.has_expected_return = false;
+        self.split_mode = false;
+        self.extra_amounts.clear();
+        self.form_linked_to = Some(loan.id);
+        self.current_tab = Tab::AddTransaction;
+        self.status_message = String::new();
+    }
+
+    /// Distinct person names from the most recently entered transactions, newest first.
+    fn recent_people(&self, limit: usize) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for t in self.db.transactions.iter().rev() {
+            if names.len() == limit {
+                break;
+            }
+            if !names.contains(&t.person.name) {
+                names.push(t.person.name.clone());
+            }
+        }
+        names
+    }
+
+    fn form_datetime(&self) -> Result<DateTime<Utc>, String> {
+        let time = NaiveTime::from_hms_opt(self.selected_hour, self.selected_minute, 0)
+            .ok_or_else(|| {
+                format!(
+                    "⚠️ Invalid time {:02}:{:02}",
+                    self.selected_hour, self.selected_minute
+                )
+            })?;
+        Ok(local_to_utc(NaiveDateTime::new(self.selected_date, time)))
+    }
+
+    fn form_transaction(
+        &self,
+        person_name: &str,
+        amount: f64,
+        direction: Direction,
+        datetime: DateTime<Utc>,
+        attachment_path: Option<String>,
+    ) -> Transaction {
+        let mut transaction = Transaction::new(
+            self.db.person_contact(person_name),
+            amount,
+            self.money_type,
+            direction,
+            datetime,
+        );
+        if self.has_expected_return {
+            transaction.expected_return_date = Some(self.expected_return_date);
+        }
+        transaction.attachment_path = attachment_path;
+        transaction.note = self.note.trim().to_string();
+        transaction
+    }
+
+    fn add_from_form(&mut self, confirmed: bool) {
+        if !confirmed
+            && self
+                .form_amount()
+                .is_ok_and(|amount| self.settings.is_large_amount(amount.abs()))
+        {
+            self.confirm_large_amount = true;
+            return;
+        }
+
+        if self.split_mode {
+            self.add_split_transactions();
+        } else {
+            self.add_single_transaction();
+        }
+    }
+
+    fn show_large_amount_confirm(&mut self, ctx: &egui::Context) {
+        let mut add = false;
+
+        egui::Window::new("⚠️ Large amount")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} is above your warning threshold of {}.",
+                    self.settings
+                        .format_money(self.money_type, self.form_amount().unwrap_or(0.0)),
+                    self.settings
+                        .format_money(self.money_type, self.settings.large_amount_threshold)
+                ));
+                ui.label("Double-check for an extra zero before adding it.");
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    if ui.button("✅ Add anyway").clicked() {
+                        add = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.confirm_large_amount = false;
+                    }
+                });
+            });
+
+        if add {
+            self.confirm_large_amount = false;
+            self.add_from_form(true);
+        }
+    }
+
+    /// The entry as a sentence, e.g. "Lent $50.00 USD to Dave, due 2024-06-01".
+    fn form_preview(&self) -> String {
+        let mut amounts = vec![self
+            .settings
+            .format_money_code(self.money_type, self.form_amount().unwrap_or(0.0))];
+        if !self.split_mode {
+            for (amount, money_type) in self.form_extra_amounts().unwrap_or_default() {
+                amounts.push(self.settings.format_money_code(money_type, amount));
+            }
+        }
+        let amount = amounts.join(" + ");
+
+        let person = if self.split_mode {
+            let count = self
+                .split_people
+                .iter()
+                .filter(|share| !share.name.trim().is_empty())
+                .count();
+            format!("{} people", count)
+        } else {
+            short_name(self.person_name.trim())
+        };
+
+        let mut preview = match self.direction {
+            Direction::Lent => format!("Lent {} to {}", amount, person),
+            Direction::Borrowed => format!("Borrowed {} from {}", amount, person),
+            Direction::Returned => format!("Got {} back from {}", amount, person),
+            Direction::Repaid => format!("Repaid {} to {}", amount, person),
+            Direction::Gift => format!("Gift of {} with {}", amount, person),
+            Direction::Adjustment => format!("Adjusted {} by {}", person, amount),
+        };
+        if self.selected_date != Local::now().date_naive() {
+            preview.push_str(&format!(" on {}", self.selected_date.format("%Y-%m-%d")));
+        }
+        if self.has_expected_return
+            && matches!(self.direction, Direction::Lent | Direction::Borrowed)
+        {
+            preview.push_str(&format!(
+                ", due {}",
+                self.expected_return_date.format("%Y-%m-%d")
+            ));
+        }
+        preview
+    }
+
+    /// Amount typed into the form; adjustments may be negative but not zero.
+    fn form_amount(&self) -> Result<f64, &'static str> {
+        self.parse_form_amount(&self.amount)
+    }
+
+    /// Parts of the entry in further currencies, or the first one that doesn't parse.
+    fn form_extra_amounts(&self) -> Result<Vec<(f64, MoneyType)>, MoneyType> {
+        self.extra_amounts
+            .iter()
+            .map(|(amount, money_type)| {
+                self.parse_form_amount(amount)
+                    .map(|amount| (amount, *money_type))
+                    .map_err(|_| *money_type)
+            })
+            .collect()
+    }
+
+    fn parse_form_amount(&self, input: &str) -> Result<f64, &'static str> {
+        if self.split_mode {
+            return parse_amount(input);
+        }
+        parse_direction_amount(self.direction, input)
+    }
+
+    fn add_single_transaction(&mut self) {
+        let Ok(amount) = self.form_amount() else {
+            self.status_message = "⚠️ Invalid amount".to_string();
+            return;
+        };
+
+        if self.person_name.trim().is_empty() {
+            self.status_message = "⚠️ Invalid input: name required".to_string();
+            return;
+        }
+
+        let extras = match self.form_extra_amounts() {
+            Ok(extras) => extras,
+            Err(money_type) => {
+                self.status_message = format!(
+                    "⚠️ Invalid {} amount",
+                    self.settings.currency_code(money_type)
+                );
+                return;
+            }
+        };
+
+        let datetime = match self.form_datetime() {
+            Ok(datetime) => datetime,
+            Err(e) => {
+                self.status_message = e;
+                return;
+            }
+        };
+
+        let mut transaction = self.form_transaction(
+            self.person_name.trim(),
+            amount,
+            self.direction,
+            datetime,
+            self.attachment_path.clone(),
+        );
+        transaction.linked_to = self.form_linked_to.take();
+        if !extras.is_empty() {
+            transaction.id = self.db.next_id();
+            transaction.group_id = Some(transaction.id);
+        }
+        let group_id = transaction.group_id;
+
+        self.db.add_transaction(transaction);
+        for (amount, money_type) in &extras {
+            let mut part = self.form_transaction(
+                self.person_name.trim(),
+                *amount,
+                self.direction,
+                datetime,
+                self.attachment_path.clone(),
+            );
+            part.money_type = *money_type;
+            part.group_id = group_id;
+            self.db.add_transaction(part);
+        }
+        self.persist();
+        self.status_message = if extras.is_empty() {
+            "✅ Transaction added successfully!".to_string()
+        } else {
+            format!("✅ Added as {} transactions!", extras.len() + 1)
+        };
+        self.person_name.clear();
+        self.amount.clear();
+        self.extra_amounts.clear();
+        self.note.clear();
+        self.has_expected_return = false;
+        self.attachment_path = None;
+    }
+
+    fn add_split_transactions(&mut self) {
+        let Ok(total) = parse_amount(&self.amount) else {
+            self.status_message = "⚠️ Invalid amount".to_string();
+            return;
+        };
+
+        let people: Vec<&SplitShare> = self
+            .split_people
+            .iter()
+            .filter(|p| !p.name.trim().is_empty())
+            .collect();
+
+        if people.is_empty() || total <= 0.0 {
+            self.status_message =
+                "⚠️ Invalid input: at least one name required and amount must be positive"
+                    .to_string();
+            return;
+        }
+
+        let shares: Vec<(String, f64)> = if self.split_equal {
+            // Split in the smallest unit the currency is shown with.
+            let unit = 10f64.powi(self.settings.decimals(self.money_type) as i32);
+            let units = (total * unit).round() as i64;
+            let base = units / people.len() as i64;
+            let remainder = units % people.len() as i64;
+            if base == 0 {
+                self.status_message = format!(
+                    "⚠️ {} is too little to split among {} people",
+                    self.settings.format_money(self.money_type, total),
+                    people.len()
+                );
+                return;
+            }
+            people
+                .iter()
+                .enumerate()
+                .map(|(idx, p)| {
+                    let share = base + if (idx as i64) < remainder { 1 } else { 0 };
+                    (p.name.trim().to_string(), share as f64 / unit)
+                })
+                .collect()
+        } else {
+            let mut shares = Vec::new();
+            for p in &people {
+                match p.share.parse::<f64>() {
+                    Ok(share) if share > 0.0 => shares.push((p.name.trim().to_string(), share)),
+                    _ => {
+                        self.status_message = format!("⚠️ Invalid share for {}", p.name.trim());
+                        return;
+                    }
+                }
+            }
+
+            let sum: f64 = shares.iter().map(|(_, share)| share).sum();
+            if (sum - total).abs() > 0.01 {
+                self.status_message = format!(
+                    "⚠️ Shares add up to {:.2}, but the total is {:.2}",
+                    sum, total
+                );
+                return;
+            }
+            shares
+        };
+
+        let datetime = match self.form_datetime() {
+            Ok(datetime) => datetime,
+            Err(e) => {
+                self.status_message = e;
+                return;
+            }
+        };
+
+        let group_id = self.db.next_id();
+        for (name, share) in &shares {
+            let mut transaction = self.form_transaction(
+                name,
+                *share,
+                Direction::Lent,
+                datetime,
+                self.attachment_path.clone(),
+            );
+            transaction.group_id = Some(group_id);
+            self.db.add_transaction(transaction);
+        }
+
+        self.persist();
+        self.status_message = format!("✅ Split added as {} transactions!", shares.len());
+        self.amount.clear();
+        self.split_people = vec![SplitShare::default(), SplitShare::default()];
+        self.note.clear();
+        self.has_expected_return = false;
+        self.attachment_path = None;
+    }
+
+    fn show_analysis(&mut self, ui: &mut egui::Ui) {
+        if self.db.transactions.is_empty() {
+            ui.vertical_centered(|ui| {
+                ui.add_space(50.0);
+                ui.heading("🔭 No transactions yet");
+                ui.label("Add some transactions to see detailed analysis!");
+            });
+            return;
+        }
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                let available_width = ui.available_width();
+                let content_width = (available_width - 40.0).min(1200.0);
+                let margin = (available_width - content_width) / 2.0;
+
+                ui.add_space(15.0);
+
+                ui.vertical_centered(|ui| {
+                    ui.heading(
+                        egui::RichText::new("Financial Analysis Dashboard")
+                            .size(24.0)
+                            .strong(),
+                    );
+                    if ui
+                        .checkbox(&mut self.include_archive, "Include archived transactions")
+                        .changed()
+                        && self.include_archive
+                        && self.archive.is_none()
+                    {
+                        self.archive = Some(self.db.archive());
+                    }
+                    ui.checkbox(&mut self.hide_settled_people, "Hide fully settled")
+                        .on_hover_text(
+                            "Leave people who are square out of the people grid and charts",
+                        );
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.analysis_range, "Only from");
+                        ui.add_enabled_ui(self.analysis_range, |ui| {
+                            ui.add(
+                                egui_extras::DatePickerButton::new(&mut self.analysis_from)
+                                    .id_source("analysis_from"),
+                            );
+                            ui.label("to");
+                            ui.add(
+                                egui_extras::DatePickerButton::new(&mut self.analysis_to)
+                                    .id_source("analysis_to"),
+                            );
+                        });
+                    });
+                    if self.analysis_range {
+                        ui.colored_label(
+                            egui::Color32::from_rgb(255, 200, 100),
+                            format!(
+                                "📅 Showing only transactions from {} to {}",
+                                self.analysis_from.format("%Y-%m-%d"),
+                                self.analysis_to.format("%Y-%m-%d")
+                            ),
+                        );
+                    }
+                });
+
+                ui.add_space(20.0);
+
+                let currencies: Vec<MoneyType> = {
+                    let transactions = self.analysis_transactions();
+                    self.settings
+                        .currencies()
+                        .into_iter()
+                        .filter(|money_type| {
+                            transactions.iter().any(|t| t.money_type == *money_type)
+                        })
+                        .collect()
+                };
+                if currencies.len() > 1 {
+                    ui.horizontal(|ui| {
+                        ui.add_space(margin);
+                        draw_currency_legend(ui, &self.settings, &currencies);
+                    });
+                    ui.add_space(10.0);
+                }
+
+                self.draw_analysis_headline(ui, margin, content_width);
+
+                if self.settings.exposure_warning {
+                    self.draw_exposure_warnings(ui, margin, content_width);
+                }
+                self.draw_open_ended_loans(ui, margin, content_width);
+
+                for (section, visible) in self.settings.analysis_sections() {
+                    if !visible {
+                        continue;
+                    }
+                    match section {
+                        AnalysisSection::Spotlight => {
+                            self.draw_analysis_spotlight(ui, margin, content_width)
+                        }
+                        AnalysisSection::Summary => {
+                            self.draw_analysis_summary(ui, margin, content_width)
+                        }
+                        AnalysisSection::NetBalance => {
+                            self.draw_analysis_net_balance(ui, margin, content_width)
+                        }
+                        AnalysisSection::Timeline => {
+                            self.draw_analysis_timeline(ui, margin, content_width)
+                        }
+                        AnalysisSection::Charts => {
+                            self.draw_analysis_charts(ui, margin, content_width)
+                        }
+                        AnalysisSection::PromiseKeeping => {
+                            self.draw_analysis_promise_keeping(ui, margin, content_width)
+                        }
+                        AnalysisSection::Tags => self.draw_analysis_tags(ui, margin, content_width),
+                        AnalysisSection::People => {
+                            self.draw_analysis_people(ui, margin, content_width)
+                        }
+                    }
+                    ui.add_space(30.0);
+                }
+            });
+    }
+
+    fn draw_analysis_spotlight(&mut self, ui: &mut egui::Ui, margin: f32, content_width: f32) {
+        ui.horizontal(|ui| {
+            ui.add_space(margin);
+            ui.vertical(|ui| {
+                ui.set_width(content_width);
+                self.draw_spotlight_cards(ui, content_width);
+            });
+        });
+    }
+
+    /// What is owed to me and what I owe right now, kept apart rather than netted.
+    fn draw_analysis_headline(&mut self, ui: &mut egui::Ui, margin: f32, content_width: f32) {
+        // Each person's balance counts on one side only, so someone who returned too
+        // much doesn't cancel out what another still owes.
+        let person_data = self.analysis_person_stats();
+        let lines = |sign: f64| {
+            self.settings
+                .currencies()
+                .into_iter()
+                .filter_map(|money_type| {
+                    let total: f64 = person_data
+                        .values()
+                        .filter_map(|stats| stats.by_currency.get(&money_type))
+                        .map(|c| (sign * c.net()).max(0.0))
+                        .sum();
+                    (total > 0.005).then(|| self.settings.format_money_code(money_type, total))
+                })
+                .collect::<Vec<_>>()
+        };
+        let columns = [
+            (
+                "💰 Owed to me",
+                lines(1.0),
+                self.settings.balance_color(1.0),
+            ),
+            ("💳 I owe", lines(-1.0), self.settings.balance_color(-1.0)),
+        ];
+
+        ui.horizontal(|ui| {
+            ui.add_space(margin);
+            ui.vertical(|ui| {
+                ui.set_width(content_width);
+                ui.columns(2, |cols| {
+                    for (col, (label, amounts, color)) in cols.iter_mut().zip(columns) {
+                        col.group(|ui| {
+                            ui.vertical_centered(|ui| {
+                                ui.colored_label(
+                                    color,
+                                    egui::RichText::new(label).size(16.0).strong(),
+                                );
+                                if amounts.is_empty() {
+                                    ui.label(egui::RichText::new("—").size(30.0).strong());
+                                }
+                                for amount in amounts {
+                                    ui.label(
+                                        egui::RichText::new(amount)
+                                            .size(30.0)
+                                            .strong()
+                                            .color(color),
+                                    );
+                                }
+                            });
+                        });
+                    }
+                });
+            });
+        });
+        ui.add_space(20.0);
+    }
+
+    /// Callout for open loans with no expected return date, which tend to drift forever.
+    fn draw_open_ended_loans(&mut self, ui: &mut egui::Ui, margin: f32, content_width: f32) {
+        let settled_amounts = self.calculate_settled_amounts();
+        let mut open_ended: Vec<usize> = self
+            .db
+            .transactions
+            .iter()
+            .enumerate()
+            .filter(|(idx, t)| {
+                t.direction == Direction::Lent
+                    && t.expected_return_date.is_none()
+                    && !t.settled
+                    && !self
+                        .settings
+                        .is_cleared(t.amount, settled_amounts.get(idx).copied().unwrap_or(0.0))
+            })
+            .map(|(idx, _)| idx)
+            .collect();
+        if open_ended.is_empty() {
+            return;
+        }
+        open_ended.sort_by_key(|idx| self.db.transactions[*idx].datetime);
+
+        ui.horizontal(|ui| {
+            ui.add_space(margin);
+            egui::Frame::none()
+                .fill(egui::Color32::from_rgb(70, 60, 30))
+                .rounding(8.0)
+                .inner_margin(12.0)
+                .show(ui, |ui| {
+                    ui.set_width(content_width - 24.0);
+                    egui::CollapsingHeader::new(
+                        egui::RichText::new(format!(
+                            "🕳 {} open loan(s) with no expected return date",
+                            open_ended.len()
+                        ))
+                        .color(egui::Color32::from_rgb(255, 200, 100))
+                        .strong(),
+                    )
+                    .id_source("open_ended_loans")
+                    .show(ui, |ui| {
+                        for idx in open_ended {
+                            let t = &self.db.transactions[idx];
+                            ui.horizontal(|ui| {
+                                full_name_on_hover(
+                                    ui.label(
+                                        egui::RichText::new(format!(
+                                            "{} {} — {}",
+                                            t.local_datetime().format("%Y-%m-%d"),
+                                            self.settings.format_money(t.money_type, t.amount),
+                                            short_name(&t.person.name)
+                                        ))
+                                        .color(egui::Color32::WHITE),
+                                    ),
+                                    &t.person.name,
+                                );
+                                if ui.small_button("📝 Set deadline").clicked() {
+                                    self.editing_deadline_for = Some(idx);
+                                    self.temp_new_deadline =
+                                        Local::now().date_naive() + chrono::Months::new(1);
+                                }
+                            });
+                        }
+                    });
+                });
+        });
+        ui.add_space(20.0);
+    }
+
+    /// Callout for currencies where loans still out pass the total or per-person limit.
+    fn draw_exposure_warnings(&mut self, ui: &mut egui::Ui, margin: f32, content_width: f32) {
+        let mut warnings = Vec::new();
+
+        let currency_totals = calculate_currency_totals(&self.db.transactions);
+        for money_type in self.settings.currencies() {
+            let out = currency_totals
+                .get(&money_type)
+                .map_or(0.0, |c| c.owed_to_me());
+            if out > self.settings.exposure_limit_total {
+                warnings.push(format!(
+                    "{} lent out in total (limit {})",
+                    self.settings.format_money_code(money_type, out),
+                    self.settings
+                        .format_money_code(money_type, self.settings.exposure_limit_total)
+                ));
+            }
+        }
+
+        let mut people: Vec<_> = self.calculate_person_stats().into_iter().collect();
+        people.sort_by(|a, b| a.0.cmp(&b.0));
+        for (name, stats) in people {
+            for money_type in self.settings.currencies() {
+                let out = stats
+                    .by_currency
+                    .get(&money_type)
+                    .map_or(0.0, |c| c.owed_to_me());
+                if out > self.settings.exposure_limit_person {
+                    warnings.push(format!(
+                        "{} owes {} (limit {})",
+                        name,
+                        self.settings.format_money_code(money_type, out),
+                        self.settings
+                            .format_money_code(money_type, self.settings.exposure_limit_person)
+                    ));
+                }
+            }
+        }
+
+        if warnings.is_empty() {
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            ui.add_space(margin);
+            egui::Frame::none()
+                .fill(egui::Color32::from_rgb(90, 40, 30))
+                .rounding(8.0)
+                .inner_margin(12.0)
+                .show(ui, |ui| {
+                    ui.set_width(content_width - 24.0);
+                    ui.label(
+                        egui::RichText::new("⚠️ Exposure above your limits")
+                            .color(egui::Color32::from_rgb(255, 200, 100))
+                            .strong(),
+                    );
+                    for warning in warnings {
+                        ui.label(egui::RichText::new(warning).color(egui::Color32::WHITE));
+                    }
+                });
+        });
+        ui.add_space(20.0);
+    }
+
+    fn draw_analysis_summary(&mut self, ui: &mut egui::Ui, margin: f32, content_width: f32) {
+        let currency_totals = calculate_currency_totals(&self.analysis_transactions());
+
+        ui.horizontal(|ui| {
+            ui.add_space(margin);
+            ui.vertical(|ui| {
+                ui.set_width(content_width);
+
+                let card_min_width = 160.0;
+                let card_spacing = 10.0;
+                let cards_per_row = ((content_width + card_spacing)
+                    / (card_min_width + card_spacing))
+                    .floor()
+                    .max(1.0) as usize;
+
+                egui::Grid::new("stats_grid")
+                    .spacing([card_spacing, card_spacing])
+                    .num_columns(cards_per_row)
+                    .show(ui, |ui| {
+                        let per_currency = |value: fn(&CurrencyStats) -> f64| {
+                            self.settings
+                                .currencies()
+                                .iter()
+                                .filter_map(|money_type| {
+                                    let total = value(currency_totals.get(money_type)?);
+                                    (total > 0.0).then(|| {
+                                        self.settings.format_money_code(*money_type, total)
+                                    })
+                                })
+                                .collect::<Vec<_>>()
+                        };
+                        let stats = [
+                            (
+                                "💸 Total Lent",
+                                per_currency(|c| c.lent),
+                                egui::Color32::from_rgb(255, 130, 130),
+                            ),
+                            (
+                                "🔥 Total Borrowed",
+                                per_currency(|c| c.borrowed),
+                                egui::Color32::from_rgb(120, 160, 255),
+                            ),
+                            (
+                                "✅ Total Returned",
+                                per_currency(|c| c.returned),
+                                egui::Color32::from_rgb(120, 220, 120),
+                            ),
+                            (
+                                "💳 Total Repaid",
+                                per_currency(|c| c.repaid),
+                                egui::Color32::from_rgb(200, 255, 150),
+                            ),
+                            (
+                                "🎁 Gifts",
+                                per_currency(|c| c.gifts),
+                                egui::Color32::from_rgb(230, 190, 255),
+                            ),
+                        ];
+
+                        for (idx, (label, amounts, color)) in stats.iter().enumerate() {
+                            ui.group(|ui| {
+                                ui.set_min_width(card_min_width);
+                                ui.vertical_centered(|ui| {
+                                    ui.colored_label(
+                                        *color,
+                                        egui::RichText::new(*label).size(14.0).strong(),
+                                    );
+                                    if amounts.is_empty() {
+                                        ui.label(egui::RichText::new("—").size(20.0).strong());
+                                    }
+                                    for amount in amounts {
+                                        ui.label(egui::RichText::new(amount).size(18.0).strong());
+                                    }
+                                });
+                            });
+
+                            if (idx + 1) % cards_per_row == 0 {
+                                ui.end_row();
+                            }
+                        }
+                    });
+            });
+        });
+    }
+
+    fn draw_analysis_net_balance(&mut self, ui: &mut egui::Ui, margin: f32, content_width: f32) {
+        let currency_totals = calculate_currency_totals(&self.analysis_transactions());
+
+        let mut balances_by_currency: HashMap<MoneyType, f64> = HashMap::new();
+        for currency in [MoneyType::GEL, MoneyType::USD, MoneyType::EUR] {
+            balances_by_currency.insert(currency, 0.0);
+        }
+        for (money_type, c) in &currency_totals {
+            balances_by_currency.insert(*money_type, -c.net());
+        }
+
+        ui.horizontal(|ui| {
+            ui.add_space(margin);
+            ui.group(|ui| {
+                ui.set_width(content_width);
+                ui.vertical(|ui| {
+                    ui.heading(
+                        egui::RichText::new("💼 Current Net Balance by Currency")
+                            .size(18.0)
+                            .strong(),
+                    );
+                    ui.add_space(10.0);
+
+                    // Largest balance first; ties keep the preferred currency order.
+                    let order = self.settings.currencies();
+                    let mut currencies: Vec<_> = balances_by_currency.iter().collect();
+                    currencies.sort_by_key(|(money_type, _)| {
+                        order.iter().position(|preferred| preferred == *money_type)
+                    });
+                    currencies.sort_by(|a, b| {
+                        b.1.abs()
+                            .partial_cmp(&a.1.abs())
+                            .unwrap_or(std::cmp::Ordering::Equal)
+                    });
+
+                    let card_width = 140.0;
+                    let card_spacing = 10.0;
+                    let num_cards = currencies.len() as f32;
+                    let total_width = (card_width * num_cards) + (card_spacing * (num_cards - 1.0));
+                    let left_padding = ((content_width - total_width) / 2.0).max(0.0);
+
+                    ui.horizontal(|ui| {
+                        ui.add_space(left_padding);
+                        for (idx, (currency, balance)) in currencies.iter().enumerate() {
+                            let color = if **balance > 0.0 {
+                                egui::Color32::from_rgb(100, 200, 100)
+                            } else if **balance < 0.0 {
+                                egui::Color32::from_rgb(255, 120, 120)
+                            } else {
+                                egui::Color32::GRAY
+                            };
+
+                            ui.group(|ui| {
+                                ui.set_width(card_width);
+                                ui.vertical_centered(|ui| {
+                                    ui.colored_label(
+                                        self.settings.currency_color(**currency),
+                                        self.settings.currency_code(**currency),
+                                    );
+                                    ui.colored_label(
+                                        color,
+                                        egui::RichText::new(
+                                            self.settings.format_money(**currency, **balance),
+                                        )
+                                        .size(18.0)
+                                        .strong(),
+                                    );
+                                });
+                            });
+
+                            if idx < currencies.len() - 1 {
+                                ui.add_space(card_spacing);
+                            }
+                        }
+                    });
+                });
+            });
+        });
+    }
+
+    fn draw_analysis_timeline(&mut self, ui: &mut egui::Ui, margin: f32, content_width: f32) {
+        ui.horizontal(|ui| {
+            ui.add_space(margin);
+            ui.group(|ui| {
+                ui.set_width(content_width);
+                ui.vertical(|ui| {
+                    let timeline = self.generate_balance_timeline();
+
+                    ui.horizontal(|ui| {
+                        ui.heading(
+                            egui::RichText::new("📊 Balance Timeline")
+                                .size(20.0)
+                                .strong(),
+                        );
+                        if ui.link("⬇ export data").clicked() {
+                            let rows = self
+                                .settings
+                                .currencies()
+                                .iter()
+                                .filter_map(|mt| {
+                                    timeline
+                                        .get(mt)
+                                        .map(|p| (self.settings.currency_code(*mt), p))
+                                })
+                                .flat_map(|(currency, points)| {
+                                    points.iter().map(move |[x, balance]| {
+                                        vec![
+                                            currency.clone(),
+                                            format_plot_day(*x, "%Y-%m-%d %H:%M"),
+                                            format!("{:.2}", balance),
+                                        ]
+                                    })
+                                })
+                                .collect();
+                            self.export_chart_data(
+                                "balance_timeline.csv",
+                                &["currency", "date", "balance"],
+                                rows,
+                            );
+                        }
+                        self.plot_reset_button(ui, "balance_timeline");
+                    });
+                    ui.add_space(15.0);
+
+                    let decimals: HashMap<String, usize> = MoneyType::ALL
+                        .iter()
+                        .map(|mt| {
+                            (
+                                self.settings.currency_code(*mt),
+                                self.settings.decimals(*mt),
+                            )
+                        })
+                        .collect();
+                    let plot_id = egui::Id::new("balance_timeline");
+                    let mut plot = Plot::new("balance_timeline")
+                        .id(plot_id)
+                        .legend(
+                            Legend::default()
+                                .position(egui_plot::Corner::LeftTop)
+                                .hidden_items(self.settings.hidden_timeline_series.iter().cloned()),
+                        )
+                        .show_axes([true, true])
+                        .x_axis_formatter(|mark, _, _| format_plot_day(mark.value, "%Y-%m-%d"))
+                        .label_formatter(move |name, point| {
+                            format!(
+                                "{}\n{}\n{:.*}",
+                                name,
+                                format_plot_day(point.x, "%Y-%m-%d %H:%M"),
+                                decimals.get(name).copied().unwrap_or(2),
+                                point.y
+                            )
+                        })
+                        .height(400.0)
+                        .allow_scroll(false)
+                        .allow_zoom(true)
+                        .allow_drag(true)
+                        .width(content_width - 40.0);
+                    if self.reset_plots.remove("balance_timeline") {
+                        plot = plot.reset();
+                    }
+                    plot.show(ui, |plot_ui| {
+                        let today = plot_day(Local::now().naive_local());
+                        plot_ui.vline(
+                            egui_plot::VLine::new(today)
+                                .name("today")
+                                .color(egui::Color32::GRAY)
+                                .style(egui_plot::LineStyle::dashed_loose()),
+                        );
+
+                        for (currency, points) in self
+                            .settings
+                            .currencies()
+                            .iter()
+                            .filter_map(|mt| timeline.get(mt).map(|p| (mt, p)))
+                        {
+                            let color = self.settings.currency_color(*currency);
+
+                            let name = self.settings.currency_code(*currency);
+
+                            // A lone point draws no line, so mark it instead.
+                            if points.len() == 1 {
+                                plot_ui.points(
+                                    Points::new(points.clone())
+                                        .name(&name)
+                                        .color(color)
+                                        .radius(5.0),
+                                );
+                            }
+                            let line = Line::new(points.clone())
+                                .name(name)
+                                .stroke(egui::Stroke::new(3.0, color));
+                            plot_ui.line(line);
+                        }
+                    });
+
+                    if let Some(memory) = egui_plot::PlotMemory::load(ui.ctx(), plot_id) {
+                        let hidden: BTreeSet<String> = memory.hidden_items.into_iter().collect();
+                        if hidden != self.settings.hidden_timeline_series {
+                            self.settings.hidden_timeline_series = hidden;
+                            if let Err(e) = self.settings.save() {
+                                self.status_message = format!("❌ Error saving settings: {}", e);
+                            }
+                        }
+                    }
+                });
+            });
+        });
+    }
+
+    fn draw_analysis_charts(&mut self, ui: &mut egui::Ui, margin: f32, content_width: f32) {
+        ui.horizontal(|ui| {
+            ui.add_space(margin);
+            ui.vertical(|ui| {
+                ui.set_width(content_width);
+
+                let chart_min_width = 300.0;
+                let use_single_column = content_width < (chart_min_width * 2.0 + 20.0);
+
+                if use_single_column {
+                    self.draw_outstanding_chart(ui, content_width);
+                    ui.add_space(20.0);
+                    self.draw_return_rate_chart(ui, content_width);
+                } else {
+                    let chart_width = (content_width - 20.0) / 2.0;
+                    ui.columns(2, |columns| {
+                        self.draw_outstanding_chart(&mut columns[0], chart_width);
+                        self.draw_return_rate_chart(&mut columns[1], chart_width);
+                    });
+                }
+            });
+        });
+    }
+
+    fn draw_analysis_promise_keeping(
+        &mut self,
+        ui: &mut egui::Ui,
+        margin: f32,
+        content_width: f32,
+    ) {
+        ui.horizontal(|ui| {
+            ui.add_space(margin);
+            self.draw_promise_keeping_chart(ui, content_width);
+        });
+    }
+
+    /// What is still out on open loans, per tag and currency. A loan with several tags
+    /// counts under each of them.
+    fn draw_analysis_tags(&mut self, ui: &mut egui::Ui, margin: f32, content_width: f32) {
+        const UNTAGGED: &str = "Untagged";
+
+        let transactions = self.analysis_transactions();
+        let settled_amounts = settled_amounts(&transactions);
+        let mut by_tag: BTreeMap<String, HashMap<MoneyType, (f64, f64)>> = BTreeMap::new();
+        for (idx, t) in transactions.iter().enumerate() {
+            if !matches!(t.direction, Direction::Lent | Direction::Borrowed) || t.settled {
+                continue;
+            }
+            let settled = settled_amounts.get(&idx).copied().unwrap_or(0.0);
+            if self.settings.is_cleared(t.amount, settled) {
+                continue;
+            }
+            let left = t.amount - settled;
+            let tags: Vec<&str> = if t.tags.is_empty() {
+                vec![UNTAGGED]
+            } else {
+                t.tags.iter().map(String::as_str).collect()
+            };
+            for tag in tags {
+                let entry = by_tag
+                    .entry(tag.to_string())
+                    .or_default()
+                    .entry(t.money_type)
+                    .or_default();
+                if t.direction == Direction::Lent {
+                    entry.0 += left;
+                } else {
+                    entry.1 += left;
+                }
+            }
+        }
+        let mut tags: Vec<_> = by_tag.into_iter().collect();
+        tags.sort_by_key(|(tag, _)| tag == UNTAGGED);
+
+        ui.horizontal(|ui| {
+            ui.add_space(margin);
+            ui.group(|ui| {
+                ui.set_width(content_width);
+                ui.vertical(|ui| {
+                    ui.horizontal(|ui| {
+                        ui.heading(
+                            egui::RichText::new("🏷 Outstanding by Tag")
+                                .size(16.0)
+                                .strong(),
+                        );
+                        if !tags.is_empty() && ui.link("⬇ export data").clicked() {
+                            let settings = &self.settings;
+                            let rows = tags
+                                .iter()
+                                .flat_map(|(tag, amounts)| {
+                                    settings.currencies().into_iter().filter_map(
+                                        move |money_type| {
+                                            let (owed, owe) = amounts.get(&money_type)?;
+                                            Some(vec![
+                                                tag.clone(),
+                                                settings.currency_code(money_type),
+                                                format!("{:.2}", owed),
+                                                format!("{:.2}", owe),
+                                            ])
+                                        },
+                                    )
+                                })
+                                .collect();
+                            self.export_chart_data(
+                                "outstanding_by_tag.csv",
+                                &["tag", "currency", "owed_to_me", "i_owe"],
+                                rows,
+                            );
+                        }
+                    });
+                    ui.add_space(10.0);
+
+                    if tags.is_empty() {
+                        ui.label("No open loans");
+                        return;
+                    }
+
+                    let per_currency =
+                        |amounts: &HashMap<MoneyType, (f64, f64)>,
+                         value: fn(&(f64, f64)) -> f64| {
+                            let parts: Vec<String> = self
+                                .settings
+                                .currencies()
+                                .iter()
+                                .filter_map(|mt| {
+                                    let total = value(amounts.get(mt)?);
+                                    (total > 0.005)
+                                        .then(|| self.settings.format_money_code(*mt, total))
+                                })
+                                .collect();
+                            if parts.is_empty() {
+                                "—".to_string()
+                            } else {
+                                parts.join(" · ")
+                            }
+                        };
+                    egui::Grid::new("outstanding_by_tag")
+                        .num_columns(3)
+                        .spacing([30.0, 6.0])
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.label(egui::RichText::new("Tag").strong());
+                            ui.label(egui::RichText::new("💰 Owed to me").strong());
+                            ui.label(egui::RichText::new("💳 I owe").strong());
+                            ui.end_row();
+
+                            for (tag, amounts) in &tags {
+                                if tag == UNTAGGED {
+                                    ui.label(egui::RichText::new(tag).italics().weak());
+                                } else {
+                                    ui.label(
+                                        egui::RichText::new(format!("#{}", tag))
+                                            .color(egui::Color32::LIGHT_BLUE),
+                                    );
+                                }
+                                ui.colored_label(
+                                    egui::Color32::from_rgb(120, 220, 120),
+                                    per_currency(amounts, |a| a.0),
+                                );
+                                ui.colored_label(
+                                    egui::Color32::from_rgb(255, 130, 130),
+                                    per_currency(amounts, |a| a.1),
+                                );
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+        });
+    }
+
+    fn draw_analysis_people(&mut self, ui: &mut egui::Ui, margin: f32, content_width: f32) {
+        ui.horizontal(|ui| {
+            ui.add_space(margin);
+            ui.separator();
+        });
+
+        ui.add_space(20.0);
+
+        ui.vertical_centered(|ui| {
+            ui.heading(
+                egui::RichText::new("👥 Individual Statistics")
+                    .size(20.0)
+                    .strong(),
+            );
+        });
+
+        ui.add_space(15.0);
+
+        ui.horizontal(|ui| {
+            ui.add_space(margin);
+            ui.vertical(|ui| {
+                ui.set_width(content_width);
+                ui.horizontal(|ui| {
+                    ui.label("🔍 Search person:");
+                    if ui
+                        .add(
+                            egui::TextEdit::singleline(&mut self.search_input).desired_width(200.0),
+                        )
+                        .changed()
+                    {
+                        self.search_edited_at = Some(ui.input(|i| i.time));
+                    }
+                    ui.separator();
+                    ui.checkbox(&mut self.compact_people, "Compact view");
+                    ui.separator();
+                    if ui.button("👥 Find duplicate people").clicked() {
+                        self.show_duplicates = true;
+                        self.duplicate_choices.clear();
+                    }
+                });
+            });
+        });
+
+        ui.add_space(15.0);
+
+        let person_data = self.dashboard_person_stats();
+        let mut people: Vec<_> = person_data.iter().collect();
+        people.sort_by(|a, b| {
+            let a_reliability = a.1.return_rate().unwrap_or(0.0);
+            let b_reliability = b.1.return_rate().unwrap_or(0.0);
+            match b_reliability
+                .partial_cmp(&a_reliability)
+                .unwrap_or(std::cmp::Ordering::Equal)
+            {
+                std::cmp::Ordering::Equal => a.0.cmp(b.0),
+                other => other,
+            }
+        });
+
+        people.retain(|(name, _)| {
+            name.to_lowercase()
+                .contains(&self.search_query.to_lowercase())
+        });
+        people.sort_by_key(|(name, _)| !self.settings.pinned_people.contains(*name));
+
+        ui.horizontal(|ui| {
+            ui.add_space(margin);
+            ui.vertical(|ui| {
+                ui.set_width(content_width);
+
+                if self.compact_people {
+                    for (name, stats) in people.iter() {
+                        self.draw_person_card(ui, name, stats, true);
+                    }
+                } else {
+                    let card_width = 340.0;
+                    let card_spacing = 15.0;
+                    let columns = ((content_width + card_spacing) / (card_width + card_spacing))
+                        .floor()
+                        .max(1.0) as usize;
+
+                    egui::Grid::new("people_grid")
+                        .spacing([card_spacing, card_spacing])
+                        .num_columns(columns)
+                        .show(ui, |ui| {
+                            for (idx, (name, stats)) in people.iter().enumerate() {
+                                self.draw_person_card(ui, name, stats, false);
+                                if (idx + 1) % columns == 0 {
+                                    ui.end_row();
+                                }
+                            }
+                        });
+                }
+
+                if people.len() > 1 {
+                    ui.add_space(15.0);
+                    self.draw_people_totals(ui, &people);
+                }
+            });
+        });
+    }
+
+    /// Sums over the people listed above, so it follows the search filter.
+    fn draw_people_totals(&self, ui: &mut egui::Ui, people: &[(&String, &PersonStats)]) {
+        let mut totals: HashMap<MoneyType, CurrencyStats> = HashMap::new();
+        for (_, stats) in people {
+            for (money_type, c) in &stats.by_currency {
+                let total = totals.entry(*money_type).or_default();
+                total.lent += c.lent;
+                total.borrowed += c.borrowed;
+                total.returned += c.returned;
+                total.repaid += c.repaid;
+                total.gifts += c.gifts;
+                total.adjustments += c.adjustments;
+            }
+        }
+        let per_currency = |value: fn(&CurrencyStats) -> f64| {
+            self.settings
+                .currencies()
+                .iter()
+                .filter_map(|mt| {
+                    let total = value(totals.get(mt)?);
+                    (total.abs() > 0.005).then(|| self.settings.format_money_code(*mt, total))
+                })
+                .collect::<Vec<_>>()
+                .join(" · ")
+        };
+
+        ui.group(|ui| {
+            ui.set_width(ui.available_width());
+            ui.horizontal_wrapped(|ui| {
+                ui.label(
+                    egui::RichText::new(format!("Σ Totals for {} people", people.len())).strong(),
+                );
+                ui.separator();
+                for money_type in self.settings.currencies() {
+                    if let Some(c) = totals.get(&money_type) {
+                        ui.colored_label(
+                            self.settings.balance_color(c.net()),
+                            egui::RichText::new(
+                                self.settings.format_balance_code(money_type, c.net()),
+                            )
+                            .strong(),
+                        );
+                    }
+                }
+                ui.separator();
+                ui.label(format!("📤 Lent: {}", per_currency(|c| c.lent)));
+                ui.separator();
+                ui.label(format!("✅ Returned: {}", per_currency(|c| c.returned)));
+                ui.separator();
+                if totals.len() > 1 {
+                    ui.label("Return Rate: N/A").on_hover_text(NO_RATES_NOTE);
+                } else if let Some(c) = totals.values().next().filter(|c| c.lent > 0.0) {
+                    ui.label(format!("Return Rate: {:.1}%", c.returned / c.lent * 100.0));
+                } else {
+                    ui.label("Return Rate: N/A");
+                }
+            });
+        });
+    }
+
+    fn open_person_detail(&mut self, name: &str) {
+        self.person_detail = Some(self.db.person_contact(name));
+    }
+
+    fn show_person_detail(&mut self, ctx: &egui::Context) {
+        let Some(mut contact) = self.person_detail.take() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut save_contact = false;
+        let mut detach = false;
+
+        egui::Window::new(format!("👤 {}", short_name(&contact.name)))
+            .id(egui::Id::new("person_detail"))
+            .collapsible(false)
+            .resizable(true)
+            .default_width(420.0)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("🗗 Detach")
+                        .on_hover_text("Open in its own window")
+                        .clicked()
+                    {
+                        detach = true;
+                    }
+                });
+                self.draw_person_overview(ui, &contact.name);
+
+                ui.add_space(10.0);
+                ui.label(egui::RichText::new("📇 Contact").strong());
+
+                egui::Grid::new("person_contact")
+                    .num_columns(3)
+                    .spacing([10.0, 8.0])
+                    .show(ui, |ui| {
+                        let mut phone = contact.phone.clone().unwrap_or_default();
+                        ui.label("📞 Phone:");
+                        if ui.text_edit_singleline(&mut phone).changed() {
+                            contact.phone =
+                                Some(phone.trim().to_string()).filter(|p| !p.is_empty());
+                        }
+                        if let Some(ref phone) = contact.phone {
+                            if ui.small_button("📋 Copy").clicked() {
+                                ui.output_mut(|o| o.copied_text = phone.clone());
+                                self.status_message = "✅ Phone number copied".to_string();
+                            }
+                        }
+                        ui.end_row();
+
+                        let mut email = contact.email.clone().unwrap_or_default();
+                        ui.label("✉ Email:");
+                        if ui.text_edit_singleline(&mut email).changed() {
+                            contact.email =
+                                Some(email.trim().to_string()).filter(|e| !e.is_empty());
+                        }
+                        if let Some(ref email) = contact.email {
+                            ui.hyperlink_to("✉ Write", format!("mailto:{}", email));
+                        }
+                        ui.end_row();
+                    });
+
+                ui.add_space(10.0);
+                if ui.button("💾 Save contact").clicked() {
+                    save_contact = true;
+                }
+            });
+
+        if save_contact {
+            self.db.update_person_contact(&contact);
+            self.db.record_audit(
+                AuditAction::Edited,
+                format!("Contact details of {} updated", contact.name),
+            );
+            self.persist();
+            self.status_message = "✅ Contact saved!".to_string();
+        }
+
+        if detach {
+            if !self.detached_people.contains(&contact.name) {
+                self.detached_people.push(contact.name.clone());
+            }
+            open = false;
+        }
+
+        if open {
+            self.person_detail = Some(contact);
+        }
+    }
+
+    /// Balances, deadlines and grouped entries for one person; shared by the detail
+    /// window and its detached copies.
+    fn draw_person_overview(&self, ui: &mut egui::Ui, name: &str) {
+        let person_data = self.calculate_person_stats();
+        if let Some(stats) = person_data.get(name) {
+            let count = self.db.person_transactions(name).count();
+            match stats.single_currency().zip(stats.outstanding()) {
+                Some((money_type, outstanding)) => {
+                    ui.label(format!(
+                        "Outstanding: {} across {} transaction(s)",
+                        self.settings.format_money_code(money_type, outstanding),
+                        count
+                    ));
+                }
+                None => {
+                    let parts: Vec<String> = self
+                        .settings
+                        .currencies()
+                        .into_iter()
+                        .filter_map(|money_type| {
+                            let c = stats.by_currency.get(&money_type)?;
+                            Some(self.settings.format_money_code(money_type, c.net()))
+                        })
+                        .collect();
+                    ui.label(format!(
+                        "Outstanding: {} across {} transaction(s)",
+                        parts.join(" · "),
+                        count
+                    ))
+                    .on_hover_text(NO_RATES_NOTE);
+                }
+            }
+
+            draw_currency_mismatch_badge(ui, &self.settings, stats);
+
+            if let Some((avg, later, earlier)) =
+                calculate_deadline_shift(self.db.person_transactions(name))
+            {
+                let color = if avg > 0.0 {
+                    egui::Color32::from_rgb(255, 180, 100)
+                } else {
+                    egui::Color32::from_rgb(130, 220, 130)
+                };
+                ui.horizontal(|ui| {
+                    ui.colored_label(color, format!("📝 avg extension: {:+.0} days", avg));
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "({} pushed later, {} moved earlier)",
+                            later, earlier
+                        ))
+                        .weak(),
+                    );
+                });
+            }
+
+            ui.add_space(10.0);
+            ui.label(egui::RichText::new("🤝 Net settlement").strong());
+
+            let currencies: Vec<_> = self
+                .settings
+                .currencies()
+                .into_iter()
+                .filter_map(|money_type| Some((money_type, stats.by_currency.get(&money_type)?)))
+                .filter(|(_, c)| c.owed_to_me().abs() > 0.01 || c.i_owe().abs() > 0.01)
+                .collect();
+
+            if currencies.is_empty() {
+                ui.label(egui::RichText::new("All square").weak());
+            }
+
+            for (money_type, c) in &currencies {
+                let net = c.net();
+                let (text, color) = if net > 0.01 {
+                    (
+                        format!(
+                            "Net: they owe you {}",
+                            self.settings.format_money(*money_type, net)
+                        ),
+                        egui::Color32::from_rgb(255, 130, 130),
+                    )
+                } else if net < -0.01 {
+                    (
+                        format!(
+                            "Net: you owe them {}",
+                            self.settings.format_money(*money_type, -net)
+                        ),
+                        egui::Color32::from_rgb(130, 220, 130),
+                    )
+                } else {
+                    (
+                        format!(
+                            "Net: square in {}",
+                            self.settings.currency_code(*money_type)
+                        ),
+                        egui::Color32::GRAY,
+                    )
+                };
+
+                ui.horizontal(|ui| {
+                    ui.colored_label(color, egui::RichText::new(text).strong());
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "(they owe {}, you owe {})",
+                            self.settings.format_money(*money_type, c.owed_to_me()),
+                            self.settings.format_money(*money_type, c.i_owe())
+                        ))
+                        .weak(),
+                    );
+                });
+            }
+
+            let deadlines =
+                match_deadline_returns(&stats.lent_transactions, &stats.return_transactions);
+            if !deadlines.is_empty() {
+                ui.add_space(10.0);
+                ui.label(egui::RichText::new("📅 Expected vs actual return").strong());
+                draw_deadline_chart(ui, name, &deadlines);
+            }
+        }
+
+        let mut groups: BTreeMap<u64, Vec<&Transaction>> = BTreeMap::new();
+        for t in self.db.person_transactions(name) {
+            if let Some(group_id) = t.group_id {
+                groups.entry(group_id).or_default().push(t);
+            }
+        }
+        groups.retain(|_, parts| parts.len() > 1);
+        if !groups.is_empty() {
+            ui.add_space(10.0);
+            ui.label(egui::RichText::new("🧾 Entries in several currencies").strong());
+            for parts in groups.values() {
+                let amounts = parts
+                    .iter()
+                    .map(|t| self.settings.format_money_code(t.money_type, t.amount))
+                    .collect::<Vec<_>>()
+                    .join(" + ");
+                ui.label(format!(
+                    "{} {:?} {}",
+                    parts[0].local_datetime().format("%Y-%m-%d"),
+                    parts[0].direction,
+                    amounts
+                ));
+            }
+        }
+    }
+
+    /// Person details opened in their own OS windows, redrawn with the main one.
+    fn show_detached_people(&mut self, ctx: &egui::Context) {
+        let mut closed = Vec::new();
+        for name in &self.detached_people {
+            let title = format!("👤 {}", short_name(name));
+            ctx.show_viewport_immediate(
+                egui::ViewportId::from_hash_of(("person_detail", name)),
+                egui::ViewportBuilder::default()
+                    .with_title(&title)
+                    .with_inner_size([440.0, 600.0]),
+                |ctx, class| {
+                    if class == egui::ViewportClass::Embedded {
+                        let mut open = true;
+                        egui::Window::new(&title)
+                            .id(egui::Id::new(("detached_person", name)))
+                            .open(&mut open)
+                            .show(ctx, |ui| self.draw_person_overview(ui, name));
+                        if !open {
+                            closed.push(name.clone());
+                        }
+                        return;
+                    }
+
+                    egui::CentralPanel::default().show(ctx, |ui| {
+                        egui::ScrollArea::vertical()
+                            .auto_shrink([false; 2])
+                            .show(ui, |ui| self.draw_person_overview(ui, name));
+                    });
+                    if ctx.input(|i| i.viewport().close_requested()) {
+                        closed.push(name.clone());
+                    }
+                },
+            );
+        }
+        self.detached_people.retain(|name| !closed.contains(name));
+    }
+
+    fn draw_spotlight_cards(&self, ui: &mut egui::Ui, width: f32) {
+        let person_data = self.analysis_person_stats();
+
+        // Amounts in different currencies can't be compared, so there is one per currency.
+        let largest_debtors: Vec<_> = self
+            .settings
+            .currencies()
+            .into_iter()
+            .filter_map(|currency| {
+                person_data
+                    .iter()
+                    .filter_map(|(name, stats)| {
+                        let net = stats.by_currency.get(&currency)?.net();
+                        (net > 0.01).then_some((name, currency, net))
+                    })
+                    .max_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal))
+            })
+            .collect();
+        let mixed = person_data
+            .values()
+            .filter(|stats| stats.has_lent() && stats.single_currency().is_none())
+            .count();
+
+        let mut return_rates: Vec<_> = person_data
+            .iter()
+            .filter_map(|(name, stats)| Some((name, stats.return_rate()?)))
+            .collect();
+        return_rates.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.0.cmp(b.0))
+        });
 
-                people.retain(|(name, _)| {
-                    name.to_lowercase()
-                        .contains(&self.search_query.to_lowercase())
-                });
+        let mut spotlights = Vec::new();
+        for (name, currency, outstanding) in largest_debtors {
+            spotlights.push((
+                "🏦 Largest Debtor",
+                name.clone(),
+                self.settings.format_money_code(currency, outstanding),
+                egui::Color32::from_rgb(255, 130, 130),
+            ));
+        }
+        if let Some((name, rate)) = return_rates.first() {
+            spotlights.push((
+                "🏆 Best Payer",
+                (*name).clone(),
+                format!("{:.1}% returned", rate),
+                egui::Color32::from_rgb(100, 220, 100),
+            ));
+        }
+        if return_rates.len() > 1 {
+            if let Some((name, rate)) = return_rates.last() {
+                spotlights.push((
+                    "🐌 Worst Payer",
+                    (*name).clone(),
+                    format!("{:.1}% returned", rate),
+                    egui::Color32::from_rgb(255, 200, 100),
+                ));
+            }
+        }
 
-                ui.horizontal(|ui| {
-                    ui.add_space(margin);
-                    ui.vertical(|ui| {
-                        ui.set_width(content_width);
+        if spotlights.is_empty() {
+            draw_no_rates_note(ui, mixed);
+            return;
+        }
 
-                        let card_width = 340.0;
-                        let card_spacing = 15.0;
-                        let columns = ((content_width + card_spacing) / (card_width + card_spacing))
-                            .floor()
-                            .max(1.0) as usize;
+        let card_spacing = 10.0;
+        let card_width = ((width - card_spacing * (spotlights.len() as f32 - 1.0))
+            / spotlights.len() as f32)
+            .min(260.0);
 
-                        egui::Grid::new("people_grid")
-                            .spacing([card_spacing, card_spacing])
-                            .num_columns(columns)
-                            .show(ui, |ui| {
-                                for (idx, (name, stats)) in people.iter().enumerate() {
-                                    self.draw_person_card(ui, name, stats);
-                                    if (idx + 1) % columns == 0 {
-                                        ui.end_row();
-                                    }
-                                }
-                            });
+        ui.horizontal(|ui| {
+            let total_width = card_width * spotlights.len() as f32
+                + card_spacing * (spotlights.len() as f32 - 1.0);
+            ui.add_space(((width - total_width) / 2.0).max(0.0));
+
+            for (idx, (label, name, detail, color)) in spotlights.iter().enumerate() {
+                ui.group(|ui| {
+                    ui.set_width(card_width);
+                    ui.vertical_centered(|ui| {
+                        ui.colored_label(*color, egui::RichText::new(*label).size(14.0).strong());
+                        full_name_on_hover(
+                            ui.label(egui::RichText::new(short_name(name)).size(18.0).strong()),
+                            name,
+                        );
+                        ui.label(egui::RichText::new(detail).weak());
                     });
                 });
 
-                ui.add_space(30.0);
-            });
+                if idx < spotlights.len() - 1 {
+                    ui.add_space(card_spacing);
+                }
+            }
+        });
+        // Return rates are only worked out within one currency.
+        ui.vertical_centered(|ui| draw_no_rates_note(ui, mixed));
     }
 
-    fn draw_outstanding_chart(&self, ui: &mut egui::Ui, width: f32) {
+    fn draw_outstanding_chart(&mut self, ui: &mut egui::Ui, width: f32) {
         ui.group(|ui| {
             ui.set_width(width);
             ui.vertical(|ui| {
-                ui.heading(
-                    egui::RichText::new("👥 Outstanding by Person")
-                        .size(16.0)
-                        .strong(),
-                );
-                ui.add_space(10.0);
+                // Owed to me and owed by me per person, kept apart so neither hides the other.
+                let person_data = self.dashboard_person_stats();
+                let mut rows: Vec<_> = self
+                    .settings
+                    .currencies()
+                    .into_iter()
+                    .flat_map(|currency| {
+                        person_data.iter().filter_map(move |(name, stats)| {
+                            let c = stats.by_currency.get(&currency)?;
+                            Some((name, currency, c.owed_to_me(), c.i_owe()))
+                        })
+                    })
+                    .filter(|(_, _, owed_to_me, i_owe)| *owed_to_me > 0.01 || *i_owe > 0.01)
+                    .collect();
+                rows.sort_by(|a, b| {
+                    (b.2.max(0.0) + b.3.max(0.0))
+                        .partial_cmp(&(a.2.max(0.0) + a.3.max(0.0)))
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                });
 
-                let person_data = self.calculate_person_stats();
-                let mut people: Vec<_> = person_data
+                // One currency at a time: amounts in different ones don't share an axis.
+                let mut currencies: Vec<MoneyType> = Vec::new();
+                for (_, currency, _, _) in &rows {
+                    if !currencies.contains(currency) {
+                        currencies.push(*currency);
+                    }
+                }
+                currencies.sort_by_key(|currency| {
+                    self.settings
+                        .currencies()
+                        .iter()
+                        .position(|option| option == currency)
+                });
+                let currency = self
+                    .outstanding_currency
+                    .filter(|currency| currencies.contains(currency))
+                    .or(currencies.first().copied());
+                let people: Vec<_> = rows
                     .iter()
-                    .filter(|(_, stats)| stats.outstanding.abs() > 0.01)
+                    .filter(|row| Some(row.1) == currency)
+                    .map(|(name, _, owed_to_me, i_owe)| (*name, *owed_to_me, *i_owe))
                     .collect();
-                people.sort_by(|a, b| {
-                    b.1.outstanding
-                        .abs()
-                        .partial_cmp(&a.1.outstanding.abs())
-                        .unwrap_or(std::cmp::Ordering::Equal)
+
+                ui.horizontal(|ui| {
+                    ui.heading(
+                        egui::RichText::new("👥 Outstanding by Person")
+                            .size(16.0)
+                            .strong(),
+                    );
+                    if let (Some(selected), true) = (currency, currencies.len() > 1) {
+                        egui::ComboBox::from_id_source("outstanding_currency")
+                            .selected_text(self.settings.currency_code(selected))
+                            .show_ui(ui, |ui| {
+                                for option in &currencies {
+                                    if ui
+                                        .selectable_label(
+                                            *option == selected,
+                                            self.settings.currency_code(*option),
+                                        )
+                                        .clicked()
+                                    {
+                                        self.outstanding_currency = Some(*option);
+                                    }
+                                }
+                            });
+                    }
+                    if !rows.is_empty() && ui.link("⬇ export data").clicked() {
+                        let rows = rows
+                            .iter()
+                            .map(|(name, currency, owed_to_me, i_owe)| {
+                                vec![
+                                    name.to_string(),
+                                    self.settings.currency_code(*currency),
+                                    format!("{:.2}", owed_to_me),
+                                    format!("{:.2}", i_owe),
+                                ]
+                            })
+                            .collect();
+                        self.export_chart_data(
+                            "outstanding_by_person.csv",
+                            &["person", "currency", "owed_to_me", "i_owe"],
+                            rows,
+                        );
+                    }
+                    if !people.is_empty() {
+                        self.plot_reset_button(ui, "outstanding_balances");
+                    }
                 });
+                ui.add_space(10.0);
 
                 if !people.is_empty() {
-                    Plot::new("outstanding_balances")
+                    let mut plot = Plot::new("outstanding_balances")
                         .legend(Legend::default())
                         .show_axes([true, true])
                         .height(300.0)
                         .allow_scroll(false)
-                        .width(width - 40.0)
-                        .show(ui, |plot_ui| {
+                        .width(width - 40.0);
+                    if self.reset_plots.remove("outstanding_balances") {
+                        plot = plot.reset();
+                    }
+                    plot.show(ui, |plot_ui| {
+                        for (series, sign) in [("Owed to me", 1.0), ("I owe", -1.0)] {
+                            let color = self.settings.balance_color(sign);
                             let bars: Vec<Bar> = people
                                 .iter()
                                 .enumerate()
-                                .map(|(i, (name, stats))| {
-                                    let color = if stats.outstanding > 0.0 {
-                                        egui::Color32::from_rgb(255, 130, 130)
+                                .filter_map(|(i, (name, owed_to_me, i_owe))| {
+                                    let value = if sign > 0.0 { *owed_to_me } else { *i_owe };
+                                    if value <= 0.01 {
+                                        return None;
+                                    }
+                                    let bar = Bar::new(i as f64, sign * value).fill(color);
+                                    Some(if self.settings.high_contrast {
+                                        let (mark, stroke_color) = if sign > 0.0 {
+                                            ("+", egui::Color32::BLACK)
+                                        } else {
+                                            ("−", egui::Color32::WHITE)
+                                        };
+                                        bar.name(format!("{} {}", mark, name))
+                                            .stroke(egui::Stroke::new(2.0, stroke_color))
                                     } else {
-                                        egui::Color32::from_rgb(130, 220, 130)
-                                    };
-                                    Bar::new(i as f64, stats.outstanding)
-                                        .name(name.as_str())
-                                        .fill(color)
+                                        bar.name(name.as_str())
+                                    })
                                 })
                                 .collect();
-                            plot_ui.bar_chart(BarChart::new(bars).width(0.7));
-                        });
+                            plot_ui.bar_chart(
+                                BarChart::new(bars).width(0.7).name(series).color(color),
+                            );
+                        }
+                    });
                 } else {
                     ui.label("No outstanding balances");
                 }
@@ -884,49 +4632,72 @@ impl BankingApp {
         });
     }
 
-    fn draw_return_rate_chart(&self, ui: &mut egui::Ui, width: f32) {
+    fn draw_return_rate_chart(&mut self, ui: &mut egui::Ui, width: f32) {
         ui.group(|ui| {
             ui.set_width(width);
             ui.vertical(|ui| {
-                ui.heading(
-                    egui::RichText::new("📈 Return Rate by Person")
-                        .size(16.0)
-                        .strong(),
-                );
-                ui.add_space(10.0);
-
-                let person_data = self.calculate_person_stats();
+                let person_data = self.dashboard_person_stats();
+                let mixed = person_data
+                    .values()
+                    .filter(|stats| stats.has_lent() && stats.return_rate().is_none())
+                    .count();
                 let mut people: Vec<_> = person_data
                     .iter()
-                    .filter(|(_, stats)| stats.lent > 0.0)
-                    .map(|(name, stats)| (name, (stats.returned / stats.lent) * 100.0))
+                    .filter_map(|(name, stats)| Some((name, stats.return_rate()?)))
                     .collect();
                 people.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
 
+                ui.horizontal(|ui| {
+                    ui.heading(
+                        egui::RichText::new("📈 Return Rate by Person")
+                            .size(16.0)
+                            .strong(),
+                    );
+                    if !people.is_empty() && ui.link("⬇ export data").clicked() {
+                        let rows = people
+                            .iter()
+                            .map(|(name, rate)| vec![name.to_string(), format!("{:.2}", rate)])
+                            .collect();
+                        self.export_chart_data(
+                            "return_rate_by_person.csv",
+                            &["person", "return_rate_percent"],
+                            rows,
+                        );
+                    }
+                    if !people.is_empty() {
+                        self.plot_reset_button(ui, "return_rates");
+                    }
+                });
+                draw_no_rates_note(ui, mixed);
+                ui.add_space(10.0);
+
                 if !people.is_empty() {
-                    Plot::new("return_rates")
+                    let mut plot = Plot::new("return_rates")
                         .legend(Legend::default())
                         .show_axes([true, true])
                         .height(300.0)
                         .allow_scroll(false)
-                        .width(width - 40.0)
-                        .show(ui, |plot_ui| {
-                            let bars: Vec<Bar> = people
-                                .iter()
-                                .enumerate()
-                                .map(|(i, (name, rate))| {
-                                    let color = if *rate >= 80.0 {
-                                        egui::Color32::from_rgb(100, 220, 100)
-                                    } else if *rate >= 50.0 {
-                                        egui::Color32::from_rgb(255, 200, 100)
-                                    } else {
-                                        egui::Color32::from_rgb(255, 130, 130)
-                                    };
-                                    Bar::new(i as f64, *rate).name(name.as_str()).fill(color)
-                                })
-                                .collect();
-                            plot_ui.bar_chart(BarChart::new(bars).width(0.7));
-                        });
+                        .width(width - 40.0);
+                    if self.reset_plots.remove("return_rates") {
+                        plot = plot.reset();
+                    }
+                    plot.show(ui, |plot_ui| {
+                        let bars: Vec<Bar> = people
+                            .iter()
+                            .enumerate()
+                            .map(|(i, (name, rate))| {
+                                let color = if *rate >= 80.0 {
+                                    egui::Color32::from_rgb(100, 220, 100)
+                                } else if *rate >= 50.0 {
+                                    egui::Color32::from_rgb(255, 200, 100)
+                                } else {
+                                    egui::Color32::from_rgb(255, 130, 130)
+                                };
+                                Bar::new(i as f64, *rate).name(name.as_str()).fill(color)
+                            })
+                            .collect();
+                        plot_ui.bar_chart(BarChart::new(bars).width(0.7));
+                    });
                 } else {
                     ui.label("No lending history");
                 }
@@ -934,44 +4705,294 @@ impl BankingApp {
         });
     }
 
-    fn calculate_person_stats(&self) -> HashMap<String, PersonStats> {
-        let mut person_data: HashMap<String, PersonStats> = HashMap::new();
+    fn plot_reset_button(&mut self, ui: &mut egui::Ui, plot_id: &'static str) {
+        if ui
+            .small_button("⟳ reset")
+            .on_hover_text("Reset zoom and position")
+            .clicked()
+        {
+            self.reset_plots.insert(plot_id);
+        }
+    }
+
+    fn save_text_report(&mut self, file_name: &str, text: &str) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Text", &["txt"])
+            .set_file_name(file_name)
+            .save_file()
+        else {
+            return;
+        };
 
-        for t in &self.db.transactions {
-            let stats = person_data
-                .entry(t.person.name.clone())
-                .or_insert(PersonStats::default());
+        match std::fs::write(&path, text) {
+            Ok(()) => self.status_message = format!("✅ Saved {}", path.display()),
+            Err(e) => self.status_message = format!("❌ Error saving {}: {}", path.display(), e),
+        }
+    }
 
-            stats.currencies.insert(t.money_type);
+    fn export_chart_data(&mut self, file_name: &str, header: &[&str], rows: Vec<Vec<String>>) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name(file_name)
+            .save_file()
+        else {
+            return;
+        };
 
-            stats.deadline_changes_count += t.deadline_changes.len();
+        let mut csv = String::new();
+        for line in std::iter::once(header.iter().map(|h| h.to_string()).collect()).chain(rows) {
+            let line: Vec<String> = line.iter().map(|field| report::csv_field(field)).collect();
+            csv.push_str(&line.join(","));
+            csv.push('\n');
+        }
 
-            match t.direction {
-                Direction::Lent => {
-                    stats.lent += t.amount;
-                    stats.outstanding += t.amount;
-                    stats.lent_transactions.push(t.clone());
-                }
-                Direction::Borrowed => {
-                    stats.borrowed += t.amount;
-                    stats.outstanding -= t.amount;
-                }
-                Direction::Returned => {
-                    stats.returned += t.amount;
-                    stats.outstanding -= t.amount;
-                    stats.return_transactions.push(t.clone());
+        match std::fs::write(&path, csv) {
+            Ok(()) => {
+                self.status_message = format!("✅ Exported chart data to {}", path.display());
+            }
+            Err(e) => self.status_message = format!("❌ Error exporting chart data: {}", e),
+        }
+    }
+
+    fn draw_promise_keeping_chart(&mut self, ui: &mut egui::Ui, width: f32) {
+        ui.group(|ui| {
+            ui.set_width(width);
+            ui.vertical(|ui| {
+                ui.horizontal(|ui| {
+                    ui.heading(
+                        egui::RichText::new("🤞 Promise Keeping")
+                            .size(18.0)
+                            .strong(),
+                    );
+                    self.plot_reset_button(ui, "promise_keeping");
+                });
+                ui.add_space(10.0);
+
+                let person_data = self.dashboard_person_stats();
+                let mut people: Vec<_> = person_data
+                    .iter()
+                    .filter_map(|(name, stats)| {
+                        let (kept, total) = calculate_promise_keeping_rate(
+                            &stats.lent_transactions,
+                            &stats.return_transactions,
+                        )?;
+                        Some((name, kept, total))
+                    })
+                    .collect();
+
+                if people.is_empty() {
+                    ui.label("No loans with an expected return date yet");
+                    return;
                 }
-                Direction::Repaid => {
-                    stats.repaid += t.amount;
-                    stats.outstanding += t.amount;
+
+                let rate = |kept: usize, total: usize| kept as f64 / total as f64 * 100.0;
+                people.sort_by(|a, b| {
+                    rate(a.1, a.2)
+                        .partial_cmp(&rate(b.1, b.2))
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                        .then_with(|| a.0.cmp(b.0))
+                });
+
+                let kept: usize = people.iter().map(|(_, kept, _)| kept).sum();
+                let total: usize = people.iter().map(|(_, _, total)| total).sum();
+                ui.label(
+                    egui::RichText::new(format!(
+                        "Overall: {:.0}% of promises kept ({}/{})",
+                        rate(kept, total),
+                        kept,
+                        total
+                    ))
+                    .size(15.0)
+                    .strong(),
+                );
+                ui.add_space(10.0);
+
+                let mut plot = Plot::new("promise_keeping")
+                    .show_axes([false, true])
+                    .height(250.0)
+                    .allow_scroll(false)
+                    .include_y(0.0)
+                    .include_y(100.0)
+                    .width(width - 40.0);
+                if self.reset_plots.remove("promise_keeping") {
+                    plot = plot.reset();
                 }
-            }
+                plot.show(ui, |plot_ui| {
+                    let bars: Vec<Bar> = people
+                        .iter()
+                        .enumerate()
+                        .map(|(i, (name, kept, total))| {
+                            let rate = rate(*kept, *total);
+                            let color = if rate >= 80.0 {
+                                egui::Color32::from_rgb(100, 220, 100)
+                            } else if rate >= 50.0 {
+                                egui::Color32::from_rgb(255, 200, 100)
+                            } else {
+                                egui::Color32::from_rgb(255, 130, 130)
+                            };
+                            Bar::new(i as f64, rate)
+                                .name(format!("{} ({}/{})", name, kept, total))
+                                .fill(color)
+                        })
+                        .collect();
+                    plot_ui.bar_chart(BarChart::new(bars).width(0.7));
+                });
+
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(egui::RichText::new("Worst to best:").weak());
+                    for (name, kept, total) in &people {
+                        full_name_on_hover(
+                            ui.label(format!("{} {:.0}%", short_name(name), rate(*kept, *total))),
+                            name,
+                        );
+                    }
+                });
+            });
+        });
+    }
+
+    fn analysis_transactions(&self) -> Cow<'_, [Transaction]> {
+        let transactions = match &self.archive {
+            Some(archive) if self.include_archive => Cow::Owned(
+                archive
+                    .transactions
+                    .iter()
+                    .chain(&self.db.transactions)
+                    .cloned()
+                    .collect(),
+            ),
+            _ => Cow::Borrowed(&self.db.transactions[..]),
+        };
+
+        if !self.analysis_range {
+            return transactions;
         }
+        Cow::Owned(
+            transactions
+                .iter()
+                .filter(|t| {
+                    let date = t.local_datetime().date();
+                    date >= self.analysis_from && date <= self.analysis_to
+                })
+                .cloned()
+                .collect(),
+        )
+    }
 
+    fn calculate_person_stats(&self) -> HashMap<String, PersonStats> {
+        stats::calculate_person_stats(&self.db.transactions)
+    }
+
+    /// Stats over the Analysis date range, and the archive when it is included.
+    fn analysis_person_stats(&self) -> HashMap<String, PersonStats> {
+        stats::calculate_person_stats(&self.analysis_transactions())
+    }
+
+    /// `analysis_person_stats` without the people who are square, if asked to hide them.
+    fn dashboard_person_stats(&self) -> HashMap<String, PersonStats> {
+        let mut person_data = self.analysis_person_stats();
+        if self.hide_settled_people {
+            person_data.retain(|_, stats| {
+                stats
+                    .by_currency
+                    .values()
+                    .any(|c| !self.settings.is_negligible(c.lent + c.borrowed, c.net()))
+            });
+        }
         person_data
     }
 
-    fn draw_person_card(&self, ui: &mut egui::Ui, name: &str, stats: &PersonStats) {
+    fn pin_button(&mut self, ui: &mut egui::Ui, name: &str) {
+        let pinned = self.settings.pinned_people.contains(name);
+        let button = ui
+            .small_button(if pinned { "★" } else { "☆" })
+            .on_hover_text(if pinned { "Unpin" } else { "Pin to the top" });
+        if button.clicked() {
+            if pinned {
+                self.settings.pinned_people.remove(name);
+            } else {
+                self.settings.pinned_people.insert(name.to_string());
+            }
+            if let Err(e) = self.settings.save() {
+                self.status_message = format!("❌ Error saving settings: {}", e);
+            }
+        }
+    }
+
+    fn draw_person_card(
+        &mut self,
+        ui: &mut egui::Ui,
+        name: &str,
+        stats: &PersonStats,
+        compact: bool,
+    ) {
+        let currencies: Vec<(MoneyType, CurrencyStats)> = self
+            .settings
+            .currencies()
+            .iter()
+            .filter_map(|mt| stats.by_currency.get(mt).map(|c| (*mt, *c)))
+            .collect();
+        let per_currency = |value: fn(&CurrencyStats) -> f64| {
+            currencies
+                .iter()
+                .map(|(mt, c)| self.settings.format_money_code(*mt, value(c)))
+                .collect::<Vec<_>>()
+                .join(" · ")
+        };
+        let totals = [
+            format!("📤 Lent: {}", per_currency(|c| c.lent)),
+            format!("🔥 Borrowed: {}", per_currency(|c| c.borrowed)),
+            format!("✅ Returned: {}", per_currency(|c| c.returned)),
+            format!("💳 Repaid: {}", per_currency(|c| c.repaid)),
+        ];
+
+        if compact {
+            let expanded = self.expanded_people.contains(name);
+            ui.horizontal(|ui| {
+                if ui.small_button(if expanded { "⏷" } else { "⏵" }).clicked() {
+                    if expanded {
+                        self.expanded_people.remove(name);
+                    } else {
+                        self.expanded_people.insert(name.to_string());
+                    }
+                }
+                self.pin_button(ui, name);
+                let label = ui.add(
+                    egui::Label::new(egui::RichText::new(short_name(name)).strong())
+                        .sense(egui::Sense::click()),
+                );
+                if full_name_on_hover(label, name)
+                    .on_hover_text("Open details")
+                    .clicked()
+                {
+                    self.open_person_detail(name);
+                }
+                ui.separator();
+                for (money_type, c) in &currencies {
+                    ui.colored_label(
+                        self.settings.balance_color(c.net()),
+                        egui::RichText::new(
+                            self.settings.format_balance_code(*money_type, c.net()),
+                        )
+                        .strong(),
+                    );
+                }
+                ui.separator();
+                match stats.return_rate() {
+                    Some(rate) => ui.label(format!("Return Rate: {:.1}%", rate)),
+                    None if stats.has_lent() => {
+                        ui.label("Return Rate: N/A").on_hover_text(NO_RATES_NOTE)
+                    }
+                    None => ui.label("Return Rate: N/A"),
+                };
+                draw_currency_mismatch_badge(ui, &self.settings, stats);
+            });
+
+            if !expanded {
+                return;
+            }
+        }
+
         ui.vertical(|ui| {
             ui.set_width(360.0);
             ui.group(|ui| {
@@ -979,63 +5000,84 @@ impl BankingApp {
                     ui.set_min_height(250.0);
                     ui.set_width(340.0);
 
-                    ui.label(egui::RichText::new(name).strong().size(16.0));
-                    ui.separator();
+                    let label = ui.add(
+                        egui::Label::new(egui::RichText::new(short_name(name)).strong().size(16.0))
+                            .sense(egui::Sense::click()),
+                    );
+                    if full_name_on_hover(label, name)
+                        .on_hover_text("Open details")
+                        .clicked()
+                    {
+                        self.open_person_detail(name);
+                    }
 
-                    let color = if stats.outstanding > 0.0 {
-                        egui::Color32::from_rgb(255, 130, 130)
-                    } else if stats.outstanding < 0.0 {
-                        egui::Color32::from_rgb(130, 220, 130)
-                    } else {
-                        egui::Color32::GRAY
-                    };
+                    self.pin_button(ui, name);
+                    draw_currency_mismatch_badge(ui, &self.settings, stats);
 
-                    let currency_symbol = if stats.currencies.len() == 1 {
-                        *stats.currencies.iter().next().unwrap()
-                    } else {
-                        MoneyType::USD
-                    };
+                    if let Some(score) = calculate_reliability_score(stats) {
+                        let badge_color = if score >= 75.0 {
+                            egui::Color32::from_rgb(60, 160, 60)
+                        } else if score >= 50.0 {
+                            egui::Color32::from_rgb(200, 150, 40)
+                        } else {
+                            egui::Color32::from_rgb(200, 70, 70)
+                        };
+                        egui::Frame::none()
+                            .fill(badge_color)
+                            .rounding(8.0)
+                            .inner_margin(egui::Margin::symmetric(8.0, 2.0))
+                            .show(ui, |ui| {
+                                ui.label(
+                                    egui::RichText::new(format!("🛡 Reliability {:.0}", score))
+                                        .color(egui::Color32::WHITE)
+                                        .strong(),
+                                );
+                            })
+                            .response
+                            .on_hover_text(
+                                "Return rate 40%, promises kept 30%, return speed 20%, \
+                                 deadline changes 10%",
+                            );
+                    }
 
-                    ui.colored_label(
-                        color,
-                        egui::RichText::new(format!(
-                            "{}{:.2}",
-                            currency_symbol.symbol(),
-                            stats.outstanding
-                        ))
-                        .strong()
-                        .size(18.0),
-                    );
+                    ui.separator();
+
+                    for (money_type, c) in &currencies {
+                        ui.colored_label(
+                            self.settings.balance_color(c.net()),
+                            egui::RichText::new(
+                                self.settings.format_balance_code(*money_type, c.net()),
+                            )
+                            .strong()
+                            .size(18.0),
+                        );
+                    }
+
+                    let sparklines = self.person_balance_history(name);
+                    if !sparklines.is_empty() {
+                        ui.add_space(6.0);
+                        draw_sparkline(ui, &sparklines);
+                    }
 
                     ui.add_space(10.0);
-                    ui.label(format!(
-                        "📤 Lent: {}{:.2}",
-                        currency_symbol.symbol(),
-                        stats.lent
-                    ));
-                    ui.label(format!(
-                        "🔥 Borrowed: {}{:.2}",
-                        currency_symbol.symbol(),
-                        stats.borrowed
-                    ));
-                    ui.label(format!(
-                        "✅ Returned: {}{:.2}",
-                        currency_symbol.symbol(),
-                        stats.returned
-                    ));
-                    ui.label(format!(
-                        "💳 Repaid: {}{:.2}",
-                        currency_symbol.symbol(),
-                        stats.repaid
-                    ));
+                    for line in &totals {
+                        ui.label(line);
+                    }
 
-                    if stats.lent > 0.0 {
+                    if stats.has_lent() {
                         ui.add_space(12.0);
-                        let return_rate = (stats.returned / stats.lent) * 100.0;
-                        ui.colored_label(
-                            egui::Color32::LIGHT_BLUE,
-                            format!("Return Rate: {:.1}%", return_rate),
-                        );
+                        match stats.return_rate() {
+                            Some(rate) => {
+                                ui.colored_label(
+                                    egui::Color32::LIGHT_BLUE,
+                                    format!("Return Rate: {:.1}%", rate),
+                                );
+                            }
+                            None => {
+                                ui.label("Return Rate: N/A (several currencies)")
+                                    .on_hover_text(NO_RATES_NOTE);
+                            }
+                        }
 
                         if let Some(avg_days) = calculate_avg_return_time(
                             &stats.lent_transactions,
@@ -1080,14 +5122,40 @@ impl BankingApp {
         });
     }
 
+    /// Running amount owed to me in each currency after every transaction with `name`.
+    fn person_balance_history(&self, name: &str) -> Vec<(egui::Color32, Vec<f64>)> {
+        let mut sorted: Vec<&Transaction> = self.db.person_transactions(name).collect();
+        sorted.sort_by_key(|t| t.datetime);
+
+        self.settings
+            .currencies()
+            .into_iter()
+            .filter_map(|money_type| {
+                let mut balance = 0.0;
+                let points: Vec<f64> = std::iter::once(0.0)
+                    .chain(
+                        sorted
+                            .iter()
+                            .filter(|t| t.money_type == money_type)
+                            .map(|t| {
+                                balance += t.direction.outstanding_sign() * t.amount;
+                                balance
+                            }),
+                    )
+                    .collect();
+                (points.len() > 1).then(|| (self.settings.currency_color(money_type), points))
+            })
+            .collect()
+    }
+
     fn generate_balance_timeline(&self) -> HashMap<MoneyType, Vec<[f64; 2]>> {
         let mut result: HashMap<MoneyType, Vec<[f64; 2]>> = HashMap::new();
         let mut balances: HashMap<MoneyType, f64> = HashMap::new();
 
-        let mut sorted_tx = self.db.transactions.clone();
+        let mut sorted_tx = self.analysis_transactions().into_owned();
         sorted_tx.sort_by_key(|t| t.datetime);
 
-        for (idx, t) in sorted_tx.iter().enumerate() {
+        for t in &sorted_tx {
             let balance = balances.entry(t.money_type).or_insert(0.0);
 
             match t.direction {
@@ -1095,12 +5163,14 @@ impl BankingApp {
                 Direction::Borrowed => *balance += t.amount,
                 Direction::Returned => *balance += t.amount,
                 Direction::Repaid => *balance -= t.amount,
+                Direction::Gift => {}
+                Direction::Adjustment => *balance -= t.amount,
             }
 
             result
                 .entry(t.money_type)
                 .or_insert_with(Vec::new)
-                .push([idx as f64, *balance]);
+                .push([plot_day(t.local_datetime()), *balance]);
         }
 
         result
@@ -1121,7 +5191,9 @@ impl BankingApp {
                 ui.add_space(margin.max(0.0));
 
                 ui.label("🔍 Search:");
-                ui.text_edit_singleline(&mut self.search_query);
+                if ui.text_edit_singleline(&mut self.search_input).changed() {
+                    self.search_edited_at = Some(ui.input(|i| i.time));
+                }
 
                 ui.separator();
 
@@ -1157,6 +5229,58 @@ impl BankingApp {
                         );
                         ui.selectable_value(&mut self.sort_by, SortBy::Person, "👤 Person");
                     });
+
+                let date_sorted = matches!(self.sort_by, SortBy::DateNewest | SortBy::DateOldest);
+                ui.add_enabled(
+                    date_sorted,
+                    egui::Checkbox::new(&mut self.group_by_month, "Group by month"),
+                );
+
+                ui.separator();
+
+                if ui.button("📋 Audit Log").clicked() {
+                    self.show_audit_log = !self.show_audit_log;
+                }
+
+                if ui.button("📝 Deadline Changes").clicked() {
+                    self.show_deadline_history = !self.show_deadline_history;
+                }
+
+                if ui.button("📄 Export CSV").clicked() {
+                    self.csv_export = Some(false);
+                }
+
+                if ui.button("📤 Export QIF").clicked() {
+                    self.qif_export = Some(self.money_type);
+                }
+
+                if ui.button("🗄 Archive").clicked() {
+                    self.show_archive = !self.show_archive;
+                    if self.archive.is_none() {
+                        self.archive = Some(self.db.archive());
+                    }
+                }
+
+                if ui.button("📰 Weekly Digest").clicked() {
+                    self.text_report = Some((
+                        "📰 Weekly Digest",
+                        "weekly_digest.txt",
+                        report::weekly_digest(
+                            &self.db.transactions,
+                            &self.calculate_settled_amounts(),
+                            &self.settings,
+                            Utc::now(),
+                        ),
+                    ));
+                }
+
+                if ui.button("🤝 Who Owes Whom").clicked() {
+                    self.text_report = Some((
+                        "🤝 Who Owes Whom",
+                        "who_owes_whom.txt",
+                        report::reconciliation(&self.db.transactions, &self.settings, Utc::now()),
+                    ));
+                }
             });
 
             ui.add_space(10.0);
@@ -1174,7 +5298,21 @@ impl BankingApp {
             .auto_shrink([false; 2])
             .show(ui, |ui| {
                 ui.vertical_centered(|ui| {
-                    let paid_back_indices = self.calculate_paid_back_transactions();
+                    let settled_amounts = self.calculate_settled_amounts();
+                    let person_stats = self.calculate_person_stats();
+                    let has_no_matching_loan = |t: &Transaction| {
+                        let Some(c) = person_stats
+                            .get(&t.person.name)
+                            .and_then(|stats| stats.by_currency.get(&t.money_type))
+                        else {
+                            return false;
+                        };
+                        match t.direction {
+                            Direction::Returned => c.lent <= 0.0,
+                            Direction::Repaid => c.borrowed <= 0.0,
+                            _ => false,
+                        }
+                    };
 
                     let mut filtered_txs: Vec<(usize, &Transaction)> = self
                         .db
@@ -1188,6 +5326,10 @@ impl BankingApp {
                                 || format!("{:?}", t.direction)
                                     .to_lowercase()
                                     .contains(&search_lower)
+                                || t.tags
+                                    .iter()
+                                    .any(|tag| tag.to_lowercase().contains(&search_lower))
+                                || t.note.to_lowercase().contains(&search_lower)
                         })
                         .collect();
 
@@ -1211,44 +5353,205 @@ impl BankingApp {
                         }
                     }
 
+                    // Only when the search picks out one person (not a tag or amount) and
+                    // they deal in one currency; the balance runs over their whole ledger.
+                    let search_lower = self.search_query.to_lowercase();
+                    let single_ledger = filtered_txs.first().and_then(|(_, first)| {
+                        filtered_txs
+                            .iter()
+                            .all(|(_, t)| {
+                                t.person.name == first.person.name
+                                    && t.money_type == first.money_type
+                                    && t.person.name.to_lowercase().contains(&search_lower)
+                            })
+                            .then_some((first.person.name.as_str(), first.money_type))
+                    });
+
+                    let running_balances: HashMap<u64, f64> = match single_ledger {
+                        Some((name, money_type)) => {
+                            let mut ledger: Vec<&Transaction> = self
+                                .db
+                                .person_transactions(name)
+                                .filter(|t| t.money_type == money_type)
+                                .collect();
+                            ledger.sort_by_key(|t| t.datetime);
+
+                            let mut balance = 0.0;
+                            ledger
+                                .iter()
+                                .map(|t| {
+                                    balance += t.direction.outstanding_sign() * t.amount;
+                                    (t.id, balance)
+                                })
+                                .collect()
+                        }
+                        None => HashMap::new(),
+                    };
+
+                    let group_by_month = self.group_by_month
+                        && matches!(self.sort_by, SortBy::DateNewest | SortBy::DateOldest);
+
+                    let mut month_totals: HashMap<(i32, u32), Vec<(MoneyType, f64)>> =
+                        HashMap::new();
+                    if group_by_month {
+                        for (_, t) in filtered_txs.iter() {
+                            let totals = month_totals
+                                .entry((t.local_datetime().year(), t.local_datetime().month()))
+                                .or_default();
+                            let change = t.direction.outstanding_sign() * t.amount;
+                            match totals.iter_mut().find(|(mt, _)| *mt == t.money_type) {
+                                Some((_, total)) => *total += change,
+                                None => totals.push((t.money_type, change)),
+                            }
+                        }
+                    }
+
+                    let mut bulk_action = None;
+                    ui.horizontal(|ui| {
+                        ui.set_width(850.0);
+                        if ui.small_button("☑ Select shown").clicked() {
+                            self.selected_transactions
+                                .extend(filtered_txs.iter().map(|(i, _)| *i));
+                        }
+                        if self.selected_transactions.is_empty() {
+                            return;
+                        }
+
+                        ui.label(format!("{} selected", self.selected_transactions.len()));
+                        if ui.small_button("Clear").clicked() {
+                            self.selected_transactions.clear();
+                            self.confirm_bulk_delete = false;
+                        }
+                        ui.separator();
+
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.bulk_tag_input)
+                                .hint_text("tag")
+                                .desired_width(100.0),
+                        );
+                        if ui.small_button("🏷 Tag").clicked() {
+                            bulk_action = Some(BulkAction::Tag);
+                        }
+                        ui.separator();
+
+                        if self.confirm_bulk_delete {
+                            ui.colored_label(
+                                egui::Color32::from_rgb(255, 130, 130),
+                                format!("Delete {}?", self.selected_transactions.len()),
+                            );
+                            if ui.small_button("Yes, delete").clicked() {
+                                bulk_action = Some(BulkAction::Delete);
+                            }
+                            if ui.small_button("No").clicked() {
+                                self.confirm_bulk_delete = false;
+                            }
+                        } else if ui.small_button("🗑 Delete").clicked() {
+                            self.confirm_bulk_delete = true;
+                        }
+                    });
+                    ui.add_space(5.0);
+
+                    let date_sorted =
+                        matches!(self.sort_by, SortBy::DateNewest | SortBy::DateOldest);
+                    let mut current_month = None;
+                    let mut current_day = None;
+                    let mut toggle_settled = None;
+                    let mut record_return = None;
+                    let mut open_attachment = None;
+                    let mut open_externally = None;
+                    let mut clear_attachment = None;
+
                     for (i, t) in filtered_txs.iter() {
+                        let month = (t.local_datetime().year(), t.local_datetime().month());
+                        let new_month = group_by_month && current_month != Some(month);
+                        if new_month {
+                            current_month = Some(month);
+                            ui.add_space(10.0);
+                            ui.horizontal(|ui| {
+                                ui.heading(
+                                    egui::RichText::new(
+                                        t.local_datetime().format("%B %Y").to_string(),
+                                    )
+                                    .size(16.0)
+                                    .strong(),
+                                );
+                                if let Some(totals) = month_totals.get(&month) {
+                                    let subtotal = totals
+                                        .iter()
+                                        .map(|(mt, total)| self.settings.format_money(*mt, *total))
+                                        .collect::<Vec<_>>()
+                                        .join("  ");
+                                    ui.label(
+                                        egui::RichText::new(format!("Net change: {}", subtotal))
+                                            .weak(),
+                                    )
+                                    .on_hover_text(
+                                        "How much this month's entries moved the balance; \
+                                         positive means more is owed to you",
+                                    );
+                                }
+                            });
+                        }
+
+                        let day = t.local_datetime().date();
+                        let same_day = date_sorted && current_day == Some(day);
+                        if date_sorted && current_day.is_some() && !same_day && !new_month {
+                            ui.add_space(4.0);
+                            ui.add(egui::Separator::default().horizontal().shrink(200.0));
+                            ui.add_space(4.0);
+                        }
+                        current_day = Some(day);
+
                         let color = match t.direction {
                             Direction::Lent => egui::Color32::from_rgb(255, 130, 130),
                             Direction::Borrowed => egui::Color32::from_rgb(120, 160, 255),
                             Direction::Returned => egui::Color32::from_rgb(120, 220, 120),
                             Direction::Repaid => egui::Color32::from_rgb(200, 255, 150),
+                            Direction::Gift => egui::Color32::from_rgb(230, 190, 255),
+                            Direction::Adjustment => egui::Color32::from_rgb(200, 200, 200),
                         };
 
-                        let is_paid_back = paid_back_indices.contains(i);
+                        let settled = settled_amounts.get(i).copied().unwrap_or(0.0);
+                        let is_paid_back = self.settings.is_cleared(t.amount, settled);
+                        let is_partially_paid = settled > 0.0 && !is_paid_back;
 
-                        ui.group(|ui| {
+                        // Faint enough to keep text readable; light backgrounds need a bit more.
+                        let tint =
+                            color.gamma_multiply(if ui.visuals().dark_mode { 0.08 } else { 0.2 });
+                        egui::Frame::group(ui.style()).fill(tint).show(ui, |ui| {
                             ui.set_width(850.0);
                             ui.horizontal(|ui| {
+                                let mut selected = self.selected_transactions.contains(i);
+                                if ui.checkbox(&mut selected, "").changed() {
+                                    if selected {
+                                        self.selected_transactions.insert(*i);
+                                    } else {
+                                        self.selected_transactions.remove(i);
+                                    }
+                                }
                                 ui.colored_label(egui::Color32::GRAY, format!("#{}", i + 1));
                                 ui.separator();
 
                                 let name_text = if is_paid_back {
-                                    egui::RichText::new(&t.person.name).strong().strikethrough()
+                                    egui::RichText::new(short_name(&t.person.name))
+                                        .strong()
+                                        .strikethrough()
                                 } else {
-                                    egui::RichText::new(&t.person.name).strong()
+                                    egui::RichText::new(short_name(&t.person.name)).strong()
                                 };
-                                ui.label(name_text);
+                                full_name_on_hover(ui.label(name_text), &t.person.name);
                                 ui.separator();
 
                                 let amount_text = if is_paid_back {
-                                    egui::RichText::new(format!(
-                                        "{}{:.2}",
-                                        t.money_type.symbol(),
-                                        t.amount
-                                    ))
+                                    egui::RichText::new(
+                                        self.settings.format_money(t.money_type, t.amount),
+                                    )
                                     .strong()
                                     .strikethrough()
                                 } else {
-                                    egui::RichText::new(format!(
-                                        "{}{:.2}",
-                                        t.money_type.symbol(),
-                                        t.amount
-                                    ))
+                                    egui::RichText::new(
+                                        self.settings.format_money(t.money_type, t.amount),
+                                    )
                                     .strong()
                                 };
                                 ui.colored_label(color, amount_text);
@@ -1261,15 +5564,71 @@ impl BankingApp {
                                     egui::RichText::new(format!("{:?}", t.direction))
                                 };
                                 ui.label(direction_text);
-                                ui.separator();
 
-                                ui.label(
-                                    egui::RichText::new(
-                                        t.datetime.format("%Y-%m-%d %H:%M").to_string(),
+                                for tag in &t.tags {
+                                    ui.label(
+                                        egui::RichText::new(format!("#{}", tag))
+                                            .small()
+                                            .color(egui::Color32::LIGHT_BLUE),
+                                    );
+                                }
+
+                                if !t.note.is_empty() {
+                                    ui.label(
+                                        egui::RichText::new(format!(
+                                            "📝 {}",
+                                            ellipsize(&t.note, 40)
+                                        ))
+                                        .small()
+                                        .italics()
+                                        .weak(),
                                     )
-                                    .weak(),
+                                    .on_hover_text(&t.note);
+                                }
+
+                                if has_no_matching_loan(t) {
+                                    ui.colored_label(egui::Color32::YELLOW, "⚠").on_hover_text(
+                                        format!(
+                                            "No {} loan to match this against",
+                                            self.settings.currency_code(t.money_type)
+                                        ),
+                                    );
+                                }
+
+                                if is_partially_paid {
+                                    ui.colored_label(
+                                        egui::Color32::YELLOW,
+                                        format!("({:.0}% settled)", settled / t.amount * 100.0),
+                                    )
+                                    .on_hover_text(format!(
+                                        "{} of {} paid back",
+                                        self.settings.format_money(t.money_type, settled),
+                                        self.settings.format_money(t.money_type, t.amount)
+                                    ));
+                                }
+                                ui.separator();
+
+                                let when = if same_day {
+                                    t.local_datetime().format("%H:%M").to_string()
+                                } else {
+                                    t.local_datetime().format("%Y-%m-%d %H:%M").to_string()
+                                };
+                                ui.label(egui::RichText::new(when).weak()).on_hover_text(
+                                    t.local_datetime().format("%Y-%m-%d").to_string(),
                                 );
 
+                                if let Some(balance) = running_balances.get(&t.id) {
+                                    ui.separator();
+                                    ui.label(
+                                        egui::RichText::new(format!(
+                                            "Σ {}",
+                                            self.settings.format_money(t.money_type, *balance)
+                                        ))
+                                        .monospace(),
+                                    )
+                                    .on_hover_text("Running balance (what they owe you)");
+                                }
+
                                 if let Some(expected) = t.expected_return_date {
                                     ui.separator();
 
@@ -1300,34 +5659,105 @@ impl BankingApp {
                                     }
                                 }
 
-                                if t.attachment_path.is_some() {
+                                if let Some(ref path) = t.attachment_path {
                                     ui.separator();
-                                    if ui.small_button("📷").clicked() {
-                                        if let Some(ref path) = t.attachment_path {
-                                            if !self.attachment_textures.contains_key(path) {
-                                                if let Ok(img) = image::open(path) {
-                                                    let img = img.to_rgba8();
-                                                    let (w, h) = img.dimensions();
-                                                    let pixels = img.into_raw();
-                                                    let color_img =
-                                                        egui::ColorImage::from_rgba_premultiplied(
-                                                            [w as usize, h as usize],
-                                                            &pixels,
-                                                        );
-                                                    let texture = ctx.load_texture(
-                                                        path,
-                                                        color_img,
-                                                        egui::TextureOptions::LINEAR,
-                                                    );
-                                                    self.attachment_textures
-                                                        .insert(path.clone(), texture);
-                                                }
-                                            }
-                                            self.viewing_attachment = Some(path.clone());
+                                    if self.missing_attachments.contains(path) {
+                                        ui.colored_label(egui::Color32::YELLOW, "⚠ missing")
+                                            .on_hover_text(path);
+                                        if ui
+                                            .small_button("Clear")
+                                            .on_hover_text("Remove the link to the missing file")
+                                            .clicked()
+                                        {
+                                            clear_attachment = Some((*i, path.clone()));
                                         }
                                     }
                                 }
 
+                                if let Some(path) = t
+                                    .attachment_path
+                                    .as_ref()
+                                    .filter(|path| !self.missing_attachments.contains(*path))
+                                {
+                                    let thumbnail = if is_pdf(path) {
+                                        None
+                                    } else {
+                                        load_thumbnail(
+                                            &mut self.thumbnails,
+                                            &self.thumbnail_sender,
+                                            ui,
+                                            path,
+                                        )
+                                    };
+                                    let clicked = match thumbnail {
+                                        Some(texture) => ui
+                                            .add(
+                                                Image::new(&texture)
+                                                    .fit_to_exact_size(egui::vec2(
+                                                        THUMBNAIL_SIZE,
+                                                        THUMBNAIL_SIZE,
+                                                    ))
+                                                    .sense(egui::Sense::click()),
+                                            )
+                                            .on_hover_text("Open attachment")
+                                            .clicked(),
+                                        None => ui.small_button(attachment_icon(path)).clicked(),
+                                    };
+                                    if clicked {
+                                        open_attachment = Some(path.clone());
+                                    }
+                                    if ui
+                                        .small_button("🗗")
+                                        .on_hover_text("Open externally")
+                                        .clicked()
+                                    {
+                                        open_externally = Some(path.clone());
+                                    }
+                                }
+
+                                if matches!(t.direction, Direction::Lent | Direction::Borrowed)
+                                    && !is_paid_back
+                                    && !t.settled
+                                {
+                                    ui.separator();
+                                    let label = if t.direction == Direction::Lent {
+                                        "↩ Returned"
+                                    } else {
+                                        "↩ Repaid"
+                                    };
+                                    if ui
+                                        .small_button(label)
+                                        .on_hover_text("Record a payment against this loan")
+                                        .clicked()
+                                    {
+                                        record_return = Some((*i, t.amount - settled));
+                                    }
+                                }
+
+                                if matches!(t.direction, Direction::Lent | Direction::Borrowed) {
+                                    ui.separator();
+                                    if ui
+                                        .selectable_label(t.settled, "✔")
+                                        .on_hover_text("Mark as settled manually")
+                                        .clicked()
+                                    {
+                                        toggle_settled = Some(*i);
+                                    }
+                                }
+
+                                if matches!(t.direction, Direction::Lent | Direction::Borrowed) {
+                                    let reminder_button =
+                                        ui.selectable_label(t.reminder.is_some(), "🔔");
+                                    let reminder_button = match &t.reminder {
+                                        Some(reminder) => reminder_button.on_hover_text(reminder),
+                                        None => reminder_button.on_hover_text("Add a reminder"),
+                                    };
+                                    if reminder_button.clicked() {
+                                        self.editing_reminder_for = Some(*i);
+                                        self.temp_reminder = t.reminder.clone().unwrap_or_default();
+                                    }
+                                }
+
                                 ui.separator();
                                 if ui.small_button("✏").clicked() {
                                     self.edit_transaction_index = Some(*i);
@@ -1335,284 +5765,1163 @@ impl BankingApp {
                             });
                         });
                     }
+
+                    match bulk_action {
+                        Some(BulkAction::Tag) => {
+                            let tag = self
+                                .bulk_tag_input
+                                .trim()
+                                .trim_start_matches('#')
+                                .to_string();
+                            if tag.is_empty() {
+                                self.status_message = "⚠️ Enter a tag first".to_string();
+                            } else {
+                                let count =
+                                    self.db.tag_transactions(&self.selected_transactions, &tag);
+                                self.persist();
+                                self.status_message =
+                                    format!("✅ Tagged {} transaction(s) with #{}", count, tag);
+                            }
+                        }
+                        Some(BulkAction::Delete) => {
+                            let count = self.db.delete_transactions(&self.selected_transactions);
+                            self.selected_transactions.clear();
+                            self.confirm_bulk_delete = false;
+                            self.edit_transaction_index = None;
+                            self.editing_deadline_for = None;
+                            self.editing_reminder_for = None;
+                            self.persist();
+                            self.status_message = format!("✅ Deleted {} transaction(s)", count);
+                        }
+                        None => {}
+                    }
+
+                    if let Some((idx, remaining)) = record_return {
+                        self.prefill_return(idx, remaining);
+                    }
+
+                    if let Some(path) = open_attachment {
+                        self.open_attachment(ctx, &path);
+                    }
+
+                    if let Some(path) = open_externally {
+                        self.open_externally(&path);
+                    }
+
+                    if let Some((idx, path)) = clear_attachment {
+                        self.db.set_attachment(idx, None);
+                        self.missing_attachments.remove(&path);
+                        self.persist();
+                        self.status_message = "✅ Removed the missing attachment".to_string();
+                    }
+
+                    if let Some(idx) = toggle_settled {
+                        if let Some(t) = self.db.transactions.get_mut(idx) {
+                            t.settled = !t.settled;
+                            let description = format!(
+                                "#{} ({}) {}",
+                                idx + 1,
+                                t.person.name,
+                                if t.settled {
+                                    "marked as settled"
+                                } else {
+                                    "no longer marked as settled"
+                                }
+                            );
+                            self.db.record_audit(AuditAction::Edited, description);
+
+                            self.persist();
+                        }
+                    }
                 });
             });
 
-        if let Some(edit_idx) = self.editing_deadline_for {
-            let mut should_close = false;
-            let mut should_save = false;
+        let editing = self.edit_transaction_index.and_then(|idx| {
+            let t = self.db.transactions.get(idx)?;
+            Some((idx, EditDraft::new(t)))
+        });
+        if let Some((edit_idx, original)) = editing {
+            let mut draft = match self.edit_draft.take() {
+                Some(draft) if draft.id == original.id => draft,
+                _ => original.clone(),
+            };
+            let dirty = draft != original;
+
+            let mut open = true;
+            let mut should_close = false;
+            let mut should_save = false;
+
+            egui::Window::new("✏ Edit Transaction")
+                .collapsible(false)
+                .resizable(false)
+                .open(&mut open)
+                .show(ctx, |ui| {
+                    ui.label(format!("Editing transaction #{}", edit_idx + 1));
+                    ui.separator();
+
+                    egui::Grid::new("edit_transaction_form")
+                        .num_columns(2)
+                        .spacing([20.0, 8.0])
+                        .show(ui, |ui| {
+                            ui.label("👤 Person:");
+                            ui.text_edit_singleline(&mut draft.person_name);
+                            ui.end_row();
+
+                            ui.label("💰 Amount:");
+                            ui.text_edit_singleline(&mut draft.amount);
+                            ui.end_row();
+
+                            ui.label("💱 Currency:");
+                            egui::ComboBox::from_id_source("edit_money_type")
+                                .selected_text(self.settings.currency_code(draft.money_type))
+                                .show_ui(ui, |ui| {
+                                    for option in self.settings.currencies() {
+                                        ui.selectable_value(
+                                            &mut draft.money_type,
+                                            option,
+                                            self.settings.currency_code(option),
+                                        );
+                                    }
+                                });
+                            ui.end_row();
+
+                            ui.label("🔄 Direction:");
+                            egui::ComboBox::from_id_source("edit_direction")
+                                .selected_text(format!("{:?}", draft.direction))
+                                .show_ui(ui, |ui| {
+                                    for option in Direction::ALL {
+                                        ui.selectable_value(
+                                            &mut draft.direction,
+                                            option,
+                                            format!("{:?}", option),
+                                        );
+                                    }
+                                });
+                            ui.end_row();
+
+                            ui.label("📅 Date:");
+                            ui.horizontal(|ui| {
+                                ui.add(
+                                    egui_extras::DatePickerButton::new(&mut draft.date)
+                                        .id_source("edit_date"),
+                                );
+                                let two_digits = |n: f64, _| format!("{:02}", n);
+                                ui.add(
+                                    egui::DragValue::new(&mut draft.hour)
+                                        .clamp_range(0..=23)
+                                        .custom_formatter(two_digits),
+                                );
+                                ui.label(":");
+                                ui.add(
+                                    egui::DragValue::new(&mut draft.minute)
+                                        .clamp_range(0..=59)
+                                        .custom_formatter(two_digits),
+                                );
+                            });
+                            ui.end_row();
+
+                            ui.label("🏷 Tags:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut draft.tags)
+                                    .hint_text("#work #trip"),
+                            );
+                            ui.end_row();
+
+                            ui.label("📝 Note:");
+                            ui.text_edit_multiline(&mut draft.note);
+                            ui.end_row();
+
+                            ui.label("📎 Attachment:");
+                            ui.horizontal(|ui| {
+                                if ui.button("📁 Change...").clicked() {
+                                    if let Some(path) = rfd::FileDialog::new()
+                                        .add_filter("Images & PDFs", ATTACHMENT_EXTENSIONS)
+                                        .pick_file()
+                                    {
+                                        match Database::copy_attachment_to_storage(
+                                            &path.to_string_lossy().to_string(),
+                                        ) {
+                                            Ok(stored_path) => {
+                                                self.discard_staged_attachment(&draft, &original);
+                                                draft.attachment_path = Some(stored_path);
+                                            }
+                                            Err(e) => {
+                                                self.status_message =
+                                                    format!("⚠️ Failed to copy attachment: {}", e);
+                                            }
+                                        }
+                                    }
+                                }
+
+                                if let Some(ref path) = draft.attachment_path {
+                                    ui.label(
+                                        PathBuf::from(path)
+                                            .file_name()
+                                            .and_then(|n| n.to_str())
+                                            .unwrap_or("file"),
+                                    );
+                                    if ui.small_button("❌").clicked() {
+                                        self.discard_staged_attachment(&draft, &original);
+                                        draft.attachment_path = None;
+                                    }
+                                } else {
+                                    ui.label(egui::RichText::new("None").weak());
+                                }
+                            });
+                            ui.end_row();
+                        });
+
+                    ui.add_space(10.0);
+                    if self.confirm_discard == Some(EditWindow::Transaction) {
+                        ui.colored_label(
+                            egui::Color32::from_rgb(255, 200, 100),
+                            "⚠ Discard changes?",
+                        );
+                        ui.horizontal(|ui| {
+                            if ui.button("🗑 Discard").clicked() {
+                                should_close = true;
+                            }
+                            if ui.button("↩ Keep editing").clicked() {
+                                self.confirm_discard = None;
+                            }
+                        });
+                    } else {
+                        ui.horizontal(|ui| {
+                            if ui
+                                .add_enabled(dirty, egui::Button::new("💾 Save"))
+                                .clicked()
+                            {
+                                should_save = true;
+                            }
+                            if ui.button("❌ Cancel").clicked() {
+                                if dirty {
+                                    self.confirm_discard = Some(EditWindow::Transaction);
+                                } else {
+                                    should_close = true;
+                                }
+                            }
+                        });
+                    }
+                });
+
+            if !open {
+                if dirty {
+                    self.confirm_discard = Some(EditWindow::Transaction);
+                } else {
+                    should_close = true;
+                }
+            }
+
+            if should_save {
+                match self.apply_edit_draft(edit_idx, &draft) {
+                    Ok(()) => {
+                        self.persist();
+                        self.status_message = "✅ Transaction updated!".to_string();
+                        should_close = true;
+                    }
+                    Err(e) => self.status_message = format!("⚠️ {}", e),
+                }
+            } else if should_close {
+                self.discard_staged_attachment(&draft, &original);
+            }
+
+            if should_close {
+                self.edit_transaction_index = None;
+                self.confirm_discard = None;
+            } else {
+                self.edit_draft = Some(draft);
+            }
+        }
+
+        if let Some((title, file_name, text)) = self.text_report.clone() {
+            let mut open = true;
+            egui::Window::new(title)
+                .id(egui::Id::new("text_report"))
+                .collapsible(false)
+                .resizable(true)
+                .default_width(500.0)
+                .open(&mut open)
+                .show(ctx, |ui| {
+                    egui::ScrollArea::vertical()
+                        .max_height(400.0)
+                        .show(ui, |ui| {
+                            ui.add(
+                                egui::TextEdit::multiline(&mut text.as_str())
+                                    .font(egui::TextStyle::Monospace)
+                                    .desired_width(f32::INFINITY),
+                            );
+                        });
+                    ui.horizontal(|ui| {
+                        if ui.button("📋 Copy").clicked() {
+                            ui.output_mut(|o| o.copied_text = text.clone());
+                        }
+                        if ui.button("💾 Save as text").clicked() {
+                            self.save_text_report(file_name, &text);
+                        }
+                    });
+                });
+            if !open {
+                self.text_report = None;
+            }
+        }
+
+        if self.show_archive {
+            self.show_archive_window(ctx);
+        }
+
+        if self.show_duplicates {
+            self.show_duplicates_window(ctx);
+        }
+
+        if self.show_deadline_history {
+            self.show_deadline_history_window(ctx);
+        }
+
+        self.show_csv_export_window(ctx);
+        self.show_qif_export_window(ctx);
 
-            egui::Window::new("📝 Change Deadline")
+        if self.show_audit_log {
+            egui::Window::new("📋 Audit Log")
                 .collapsible(false)
-                .resizable(false)
+                .resizable(true)
+                .default_width(500.0)
+                .open(&mut self.show_audit_log)
                 .show(ctx, |ui| {
-                    if let Some(t) = self.db.transactions.get(edit_idx) {
-                        ui.label(format!("Changing deadline for: {}", t.person.name));
-                        ui.label(format!("Transaction #{}", edit_idx + 1));
-                        ui.separator();
+                    if self.db.audit_log.is_empty() {
+                        ui.label(egui::RichText::new("No changes recorded yet").weak());
+                        return;
+                    }
 
-                        ui.add_space(10.0);
+                    egui::ScrollArea::vertical()
+                        .max_height(400.0)
+                        .show(ui, |ui| {
+                            for entry in self.db.audit_log.iter().rev().take(200) {
+                                ui.horizontal(|ui| {
+                                    ui.label(
+                                        egui::RichText::new(
+                                            entry
+                                                .changed_at
+                                                .with_timezone(&Local)
+                                                .format("%Y-%m-%d %H:%M")
+                                                .to_string(),
+                                        )
+                                        .weak(),
+                                    );
+                                    ui.label(entry.action.icon());
+                                    ui.label(&entry.description);
+                                });
+                            }
+                        });
+                });
+        }
+    }
 
-                        if let Some(current_deadline) = t.expected_return_date {
-                            ui.label(format!(
-                                "Current deadline: {}",
-                                current_deadline.format("%Y-%m-%d")
-                            ));
+    fn show_csv_export_window(&mut self, ctx: &egui::Context) {
+        let Some(mut running_totals) = self.csv_export else {
+            return;
+        };
+        let mut open = true;
+        let mut export = false;
+
+        egui::Window::new("📄 Export CSV")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} transaction(s), oldest first.",
+                    self.db.transactions.len()
+                ));
+                ui.checkbox(&mut running_totals, "Add running balance columns")
+                    .on_hover_text(
+                        "The balance in each currency and the person's balance \
+                         after every row (positive means owed to you)",
+                    );
+                ui.add_space(10.0);
+                if ui.button("💾 Export…").clicked() {
+                    export = true;
+                }
+            });
 
-                            if !t.deadline_changes.is_empty() {
-                                ui.label(format!(
-                                    "Previously changed {} time(s)",
-                                    t.deadline_changes.len()
-                                ));
-                                ui.add_space(5.0);
+        self.csv_export = open.then_some(running_totals);
 
-                                egui::CollapsingHeader::new("📜 Change History").show(ui, |ui| {
-                                    for (idx, change) in t.deadline_changes.iter().enumerate() {
-                                        ui.label(format!(
-                                            "{}. {} ➡ {} (changed on {})",
-                                            idx + 1,
-                                            change.old_date.format("%Y-%m-%d"),
-                                            change.new_date.format("%Y-%m-%d"),
-                                            change.changed_at.format("%Y-%m-%d %H:%M")
-                                        ));
-                                    }
-                                });
-                            }
-                        }
+        if !export {
+            return;
+        }
 
-                        ui.add_space(15.0);
-                        ui.label("New deadline:");
-                        ui.add(egui_extras::DatePickerButton::new(
-                            &mut self.temp_new_deadline,
-                        ));
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name("transactions.csv")
+            .save_file()
+        else {
+            return;
+        };
+
+        match std::fs::write(
+            &path,
+            report::csv(&self.db.transactions, &self.settings, running_totals),
+        ) {
+            Ok(()) => {
+                self.csv_export = None;
+                self.status_message = format!("✅ Exported transactions to {}", path.display());
+            }
+            Err(e) => self.status_message = format!("❌ Error exporting CSV: {}", e),
+        }
+    }
 
-                        ui.add_space(15.0);
-                        ui.horizontal(|ui| {
-                            if ui.button("💾 Save").clicked() {
-                                should_save = true;
-                                should_close = true;
-                            }
-                            if ui.button("❌ Cancel").clicked() {
-                                should_close = true;
+    fn show_qif_export_window(&mut self, ctx: &egui::Context) {
+        let Some(mut money_type) = self.qif_export else {
+            return;
+        };
+        let mut open = true;
+        let mut export = false;
+
+        egui::Window::new("📤 Export QIF")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label("QIF files hold a single currency.");
+                ui.horizontal(|ui| {
+                    ui.label("Currency:");
+                    egui::ComboBox::from_id_source("qif_currency")
+                        .selected_text(self.settings.currency_code(money_type))
+                        .show_ui(ui, |ui| {
+                            for option in self.settings.currencies() {
+                                let count = self
+                                    .db
+                                    .transactions
+                                    .iter()
+                                    .filter(|t| t.money_type == option)
+                                    .count();
+                                ui.selectable_value(
+                                    &mut money_type,
+                                    option,
+                                    format!("{} ({})", self.settings.currency_code(option), count),
+                                );
                             }
                         });
-                    }
                 });
+                ui.add_space(10.0);
+                if ui.button("💾 Export…").clicked() {
+                    export = true;
+                }
+            });
 
-            if should_save {
-                if let Some(t) = self.db.transactions.get_mut(edit_idx) {
-                    if let Some(old_deadline) = t.expected_return_date {
-                        if old_deadline != self.temp_new_deadline {
-                            let change = DeadlineChange {
-                                old_date: old_deadline,
-                                new_date: self.temp_new_deadline,
-                                changed_at: Local::now().naive_local(),
-                            };
-                            t.deadline_changes.push(change);
-                            t.expected_return_date = Some(self.temp_new_deadline);
+        self.qif_export = open.then_some(money_type);
 
-                            if let Err(e) = self.db.save() {
-                                self.status_message = format!("❌ Error saving: {}", e);
-                            } else {
-                                self.status_message = "✅ Deadline updated!".to_string();
-                            }
-                        }
-                    }
-                }
-            }
+        if !export {
+            return;
+        }
 
-            if should_close {
-                self.editing_deadline_for = None;
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("QIF", &["qif"])
+            .set_file_name(format!("transactions_{:?}.qif", money_type))
+            .save_file()
+        else {
+            return;
+        };
+
+        match std::fs::write(&path, report::qif(&self.db.transactions, money_type)) {
+            Ok(()) => {
+                self.qif_export = None;
+                self.status_message = format!(
+                    "✅ Exported {} transactions to {}",
+                    self.settings.currency_code(money_type),
+                    path.display()
+                );
             }
+            Err(e) => self.status_message = format!("❌ Error exporting QIF: {}", e),
         }
+    }
 
-        if let Some(edit_idx) = self.edit_transaction_index {
-            let mut should_close = false;
-            let mut should_save = false;
-            let mut new_attachment: Option<Option<String>> = None;
+    fn show_deadline_history_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_deadline_history;
 
-            egui::Window::new("✏ Edit Transaction")
-                .collapsible(false)
-                .resizable(false)
-                .show(ctx, |ui| {
-                    if let Some(t) = self.db.transactions.get(edit_idx) {
-                        ui.label(format!("Editing transaction #{}", edit_idx + 1));
-                        ui.separator();
+        egui::Window::new("📝 Deadline Changes")
+            .collapsible(false)
+            .resizable(true)
+            .default_width(550.0)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("🔍 Person:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.deadline_history_filter)
+                            .desired_width(150.0),
+                    );
+                    ui.separator();
+                    ui.radio_value(
+                        &mut self.deadline_history_oldest_first,
+                        false,
+                        "Newest first",
+                    );
+                    ui.radio_value(
+                        &mut self.deadline_history_oldest_first,
+                        true,
+                        "Oldest first",
+                    );
+                });
+                ui.separator();
 
-                        ui.horizontal(|ui| {
-                            ui.label("📎 Attachment:");
-                            if ui.button("📁 Change...").clicked() {
-                                if let Some(path) = rfd::FileDialog::new()
-                                    .add_filter("Images", &["png", "jpg", "jpeg", "gif", "bmp"])
-                                    .pick_file()
-                                {
-                                    match Database::copy_attachment_to_storage(
-                                        &path.to_string_lossy().to_string(),
-                                    ) {
-                                        Ok(stored_path) => {
-                                            new_attachment = Some(Some(stored_path));
-                                        }
-                                        Err(e) => {
-                                            self.status_message =
-                                                format!("⚠️ Failed to copy attachment: {}", e);
-                                        }
-                                    }
+                let filter = self.deadline_history_filter.trim().to_lowercase();
+                let mut changes: Vec<(&Transaction, &DeadlineChange)> = self
+                    .db
+                    .transactions
+                    .iter()
+                    .filter(|t| t.person.name.to_lowercase().contains(&filter))
+                    .flat_map(|t| t.deadline_changes.iter().map(move |change| (t, change)))
+                    .collect();
+                changes.sort_by_key(|(_, change)| change.changed_at);
+                if !self.deadline_history_oldest_first {
+                    changes.reverse();
+                }
+
+                if changes.is_empty() {
+                    ui.label(egui::RichText::new("No deadline changes recorded").weak());
+                    return;
+                }
+
+                let (later, earlier, net) =
+                    deadline_shift_summary(changes.iter().map(|(_, change)| *change));
+                ui.colored_label(
+                    deadline_shift_color(net),
+                    format!(
+                        "{} pushed later, {} moved earlier, net {:+} days",
+                        later, earlier, net
+                    ),
+                );
+                ui.add_space(5.0);
+
+                egui::ScrollArea::vertical()
+                    .max_height(400.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("deadline_history_grid")
+                            .num_columns(4)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for (t, change) in changes {
+                                    let shift = (change.new_date - change.old_date).num_days();
+                                    ui.label(
+                                        egui::RichText::new(
+                                            change
+                                                .changed_at
+                                                .with_timezone(&Local)
+                                                .format("%Y-%m-%d %H:%M")
+                                                .to_string(),
+                                        )
+                                        .weak(),
+                                    );
+                                    full_name_on_hover(
+                                        ui.label(short_name(&t.person.name)),
+                                        &t.person.name,
+                                    );
+                                    ui.label(format!(
+                                        "{} ➡ {}",
+                                        change.old_date.format("%Y-%m-%d"),
+                                        change.new_date.format("%Y-%m-%d")
+                                    ));
+                                    ui.colored_label(
+                                        deadline_shift_color(shift),
+                                        format!("{:+} days", shift),
+                                    );
+                                    ui.end_row();
                                 }
-                            }
+                            });
+                    });
+            });
 
-                            if let Some(ref path) = t.attachment_path {
-                                ui.label(
-                                    PathBuf::from(path)
-                                        .file_name()
-                                        .and_then(|n| n.to_str())
-                                        .unwrap_or("file"),
+        self.show_deadline_history = open;
+    }
+
+    fn show_duplicates_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_duplicates;
+        let mut merge = None;
+        let groups = self.db.duplicate_people();
+
+        egui::Window::new("👥 Duplicate people")
+            .collapsible(false)
+            .resizable(true)
+            .default_width(450.0)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                if groups.is_empty() {
+                    ui.label(egui::RichText::new("No names differ only by case or spacing").weak());
+                    return;
+                }
+
+                ui.label("Pick the name to keep for each group:");
+                ui.add_space(5.0);
+
+                for names in &groups {
+                    let canonical = self
+                        .duplicate_choices
+                        .entry(names[0].to_lowercase())
+                        .or_insert_with(|| names[0].clone());
+                    ui.group(|ui| {
+                        ui.horizontal_wrapped(|ui| {
+                            for name in names {
+                                let count = self.db.person_transactions(name).count();
+                                ui.radio_value(
+                                    canonical,
+                                    name.clone(),
+                                    format!("{:?} ({})", name, count),
                                 );
-                                if ui.small_button("❌").clicked() {
-                                    new_attachment = Some(None);
-                                }
-                            } else {
-                                ui.label(egui::RichText::new("None").weak());
                             }
                         });
+                        if ui.button("🔗 Merge").clicked() {
+                            merge = Some((names.clone(), canonical.clone()));
+                        }
+                    });
+                }
+            });
 
-                        ui.add_space(10.0);
-                        ui.horizontal(|ui| {
-                            if ui.button("💾 Save").clicked() {
-                                should_save = true;
-                                should_close = true;
-                            }
-                            if ui.button("❌ Cancel").clicked() {
-                                should_close = true;
-                            }
-                        });
+        self.show_duplicates = open;
+
+        if let Some((names, canonical)) = merge {
+            let merged = self.db.merge_people(&names, canonical.trim());
+            self.persist();
+            self.status_message = format!(
+                "✅ Merged {} transaction(s) into {}",
+                merged,
+                canonical.trim()
+            );
+        }
+    }
+
+    fn show_archive_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_archive;
+        let mut archive_now = false;
+
+        egui::Window::new("🗄 Archive")
+            .collapsible(false)
+            .resizable(true)
+            .default_width(550.0)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Archive fully paid-back loans before");
+                    ui.add(
+                        egui_extras::DatePickerButton::new(&mut self.archive_before)
+                            .id_source("archive_before"),
+                    );
+                    if ui.button("🗄 Archive settled").clicked() {
+                        archive_now = true;
                     }
                 });
+                ui.label(
+                    egui::RichText::new(
+                        "Archived transactions are kept in archive.json and shown here read-only.",
+                    )
+                    .weak(),
+                );
+                ui.separator();
+
+                let Some(archive) = &self.archive else {
+                    return;
+                };
 
-            if let Some(new_att) = new_attachment {
-                if let Some(t) = self.db.transactions.get_mut(edit_idx) {
-                    t.attachment_path = new_att;
+                if archive.transactions.is_empty() {
+                    ui.label(egui::RichText::new("Nothing archived yet").weak());
+                    return;
                 }
-            }
 
-            if should_save {
-                let _ = self.db.save();
-                self.status_message = "✅ Transaction updated!".to_string();
-            }
+                egui::ScrollArea::vertical()
+                    .max_height(400.0)
+                    .show(ui, |ui| {
+                        for t in archive.transactions.iter().rev() {
+                            ui.horizontal(|ui| {
+                                ui.label(
+                                    egui::RichText::new(
+                                        t.local_datetime().format("%Y-%m-%d").to_string(),
+                                    )
+                                    .weak(),
+                                );
+                                ui.label(format!("{:?}", t.direction));
+                                ui.label(self.settings.format_money(t.money_type, t.amount));
+                                full_name_on_hover(
+                                    ui.label(short_name(&t.person.name)),
+                                    &t.person.name,
+                                );
+                            });
+                        }
+                    });
+            });
 
-            if should_close {
-                self.edit_transaction_index = None;
+        self.show_archive = open;
+
+        if archive_now {
+            match self
+                .db
+                .archive_settled_before(self.archive_before, &self.settings)
+            {
+                0 => {
+                    self.status_message =
+                        "⚠️ No fully paid-back transactions before that date".to_string();
+                }
+                count => {
+                    self.archive = Some(self.db.archive());
+                    self.selected_transactions.clear();
+                    self.edit_transaction_index = None;
+                    self.editing_deadline_for = None;
+                    self.editing_reminder_for = None;
+                    self.persist();
+                    self.status_message = format!("✅ Archived {} transaction(s)", count);
+                }
             }
         }
     }
 
-    fn calculate_paid_back_transactions(&self) -> std::collections::HashSet<usize> {
-        use std::collections::HashSet;
-        let mut paid_back = HashSet::new();
+    fn calculate_settled_amounts(&self) -> HashMap<usize, f64> {
+        settled_amounts(&self.db.transactions)
+    }
+}
 
-        let mut person_debts: HashMap<(String, MoneyType), Vec<(usize, f64, Direction)>> =
-            HashMap::new();
+/// Amount paid back on each `Lent`/`Borrowed` transaction, matched oldest loan first.
+/// Keyed by position in `transactions`.
+fn settled_amounts(transactions: &[Transaction]) -> HashMap<usize, f64> {
+    let mut settled = HashMap::new();
 
-        for (idx, t) in self.db.transactions.iter().enumerate() {
-            let key = (t.person.name.clone(), t.money_type);
-            person_debts
-                .entry(key)
-                .or_insert_with(Vec::new)
-                .push((idx, t.amount, t.direction));
+    let mut ledgers: HashMap<(&str, MoneyType, bool), (Vec<usize>, f64)> = HashMap::new();
+
+    for (idx, t) in transactions.iter().enumerate() {
+        if !t.direction.is_loan_flow() {
+            continue;
         }
+        let is_loan = matches!(t.direction, Direction::Lent | Direction::Borrowed);
+        let i_lent = matches!(t.direction, Direction::Lent | Direction::Returned);
+        let ledger = ledgers
+            .entry((t.person.name.as_str(), t.money_type, i_lent))
+            .or_default();
+
+        // Loans marked settled by hand leave the returns to the other loans.
+        if !is_loan {
+            ledger.1 += t.amount;
+        } else if !t.settled {
+            ledger.0.push(idx);
+        }
+    }
 
-        for debts in person_debts.values() {
-            let lent_borrowed: Vec<(usize, f64, Direction)> = debts
-                .iter()
-                .filter(|(_, _, dir)| matches!(dir, Direction::Lent | Direction::Borrowed))
-                .copied()
-                .collect();
+    for (mut loans, mut remaining_returns) in ledgers.into_values() {
+        loans.sort_by_key(|idx| transactions[*idx].datetime);
 
-            let returns: Vec<(usize, f64, Direction)> = debts
-                .iter()
-                .filter(|(_, _, dir)| matches!(dir, Direction::Returned | Direction::Repaid))
-                .copied()
-                .collect();
+        for idx in loans {
+            if remaining_returns <= 0.0 {
+                break;
+            }
+            let amount = transactions[idx].amount;
+            let applied = remaining_returns.min(amount);
+            settled.insert(idx, applied);
+            remaining_returns -= applied;
+        }
+    }
+
+    for (idx, t) in transactions.iter().enumerate() {
+        if t.settled {
+            settled.insert(idx, t.amount);
+        }
+    }
 
-            let mut remaining_returns = returns.iter().map(|(_, amount, _)| *amount).sum::<f64>();
+    settled
+}
 
-            for (idx, amount, _) in lent_borrowed.iter() {
-                if remaining_returns >= *amount {
-                    paid_back.insert(*idx);
-                    remaining_returns -= amount;
-                } else if remaining_returns > 0.0 {
-                    break;
-                } else {
-                    break;
+fn draw_currency_mismatch_badge(ui: &mut egui::Ui, settings: &Settings, stats: &PersonStats) {
+    let mismatched = stats.mismatched_currencies();
+    if mismatched.is_empty() {
+        return;
+    }
+
+    let codes = mismatched
+        .iter()
+        .map(|money_type| settings.currency_code(*money_type))
+        .collect::<Vec<_>>()
+        .join(", ");
+    ui.colored_label(
+        egui::Color32::YELLOW,
+        format!("⚠ Currency mismatch: {}", codes),
+    )
+    .on_hover_text(
+        "Returns were logged in a currency with no matching loan, \
+             so they can't settle anything. Check the currency of those entries.",
+    );
+}
+
+/// Expected return date against the actual one; points above the diagonal came back late.
+/// Loans not returned yet are drawn at today.
+fn draw_deadline_chart(
+    ui: &mut egui::Ui,
+    name: &str,
+    deadlines: &[(&Transaction, NaiveDate, Option<NaiveDate>)],
+) {
+    let day = |date: NaiveDate| date.num_days_from_ce() as f64;
+    let format_day = |value: f64| {
+        NaiveDate::from_num_days_from_ce_opt(value.round() as i32)
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .unwrap_or_default()
+    };
+    let today = Local::now().date_naive();
+
+    let mut on_time = Vec::new();
+    let mut late = Vec::new();
+    let mut open = Vec::new();
+    for (_, expected, returned_on) in deadlines {
+        match returned_on {
+            Some(date) if date <= expected => on_time.push([day(*expected), day(*date)]),
+            Some(date) => late.push([day(*expected), day(*date)]),
+            None => open.push([day(*expected), day(today)]),
+        }
+    }
+
+    let (min, max) = deadlines
+        .iter()
+        .flat_map(|(_, expected, returned_on)| [*expected, returned_on.unwrap_or(today)])
+        .fold((f64::MAX, f64::MIN), |(min, max), date| {
+            (min.min(day(date)), max.max(day(date)))
+        });
+
+    Plot::new(("deadline_chart", name))
+        .height(220.0)
+        .legend(Legend::default().position(egui_plot::Corner::LeftTop))
+        .x_axis_formatter(move |mark, _, _| format_day(mark.value))
+        .y_axis_formatter(move |mark, _, _| format_day(mark.value))
+        .label_formatter(move |name, point| {
+            format!(
+                "{}\nexpected {}\nactual {}",
+                name,
+                format_day(point.x),
+                format_day(point.y)
+            )
+        })
+        .allow_scroll(false)
+        .show(ui, |plot_ui| {
+            plot_ui.line(
+                Line::new(vec![[min - 1.0, min - 1.0], [max + 1.0, max + 1.0]])
+                    .name("Deadline")
+                    .color(egui::Color32::GRAY)
+                    .style(egui_plot::LineStyle::dashed_loose()),
+            );
+            for (points, name, color) in [
+                (on_time, "On time", egui::Color32::from_rgb(120, 220, 120)),
+                (late, "Late", egui::Color32::from_rgb(255, 120, 120)),
+                (
+                    open,
+                    "Not returned yet",
+                    egui::Color32::from_rgb(255, 200, 100),
+                ),
+            ] {
+                if !points.is_empty() {
+                    plot_ui.points(
+                        egui_plot::Points::new(points)
+                            .name(name)
+                            .color(color)
+                            .radius(4.0),
+                    );
                 }
             }
+        });
+}
+
+/// Amber for a deadline pushed later, green for one moved earlier.
+fn deadline_shift_color(days: i64) -> egui::Color32 {
+    match days.cmp(&0) {
+        std::cmp::Ordering::Greater => egui::Color32::from_rgb(255, 180, 100),
+        std::cmp::Ordering::Less => egui::Color32::from_rgb(130, 220, 130),
+        std::cmp::Ordering::Equal => egui::Color32::GRAY,
+    }
+}
+
+/// How many changes pushed the deadline later and earlier, and the net shift in days.
+fn deadline_shift_summary<'a>(
+    changes: impl IntoIterator<Item = &'a DeadlineChange>,
+) -> (usize, usize, i64) {
+    changes
+        .into_iter()
+        .fold((0, 0, 0), |(later, earlier, net), change| {
+            let shift = (change.new_date - change.old_date).num_days();
+            (
+                later + usize::from(shift > 0),
+                earlier + usize::from(shift < 0),
+                net + shift,
+            )
+        })
+}
+
+/// Position of a local time on a plot axis measured in days.
+fn plot_day(datetime: NaiveDateTime) -> f64 {
+    datetime.and_utc().timestamp() as f64 / 86_400.0
+}
+
+fn format_plot_day(day: f64, format: &str) -> String {
+    DateTime::from_timestamp((day * 86_400.0).round() as i64, 0)
+        .map(|datetime| datetime.naive_utc().format(format).to_string())
+        .unwrap_or_default()
+}
+
+/// Moves keyboard focus along `chain` on Tab / Shift+Tab, wrapping at either end.
+fn cycle_focus(ui: &egui::Ui, chain: &[egui::Response]) {
+    let Some(current) = chain.iter().position(|response| response.has_focus()) else {
+        return;
+    };
+
+    // Keep egui from moving focus itself next frame; we do it here instead.
+    ui.memory_mut(|mem| {
+        mem.set_focus_lock_filter(
+            chain[current].id,
+            egui::EventFilter {
+                tab: true,
+                vertical_arrows: true,
+                ..Default::default()
+            },
+        )
+    });
+
+    let next = ui.input_mut(|i| {
+        if i.consume_key(egui::Modifiers::SHIFT, egui::Key::Tab) {
+            Some((current + chain.len() - 1) % chain.len())
+        } else if i.consume_key(egui::Modifiers::NONE, egui::Key::Tab) {
+            Some((current + 1) % chain.len())
+        } else {
+            None
         }
+    });
+    if let Some(next) = next {
+        ui.memory_mut(|mem| {
+            mem.close_popup();
+            mem.request_focus(chain[next].id);
+        });
+    }
+}
 
-        paid_back
+/// Lets ↑/↓ pick the previous/next option of a focused, closed combo box.
+fn step_with_arrows<T: Copy + PartialEq>(
+    ui: &egui::Ui,
+    response: &egui::Response,
+    options: &[T],
+    value: &mut T,
+) {
+    if !response.has_focus() || ui.memory(|mem| mem.any_popup_open()) {
+        return;
+    }
+    let Some(current) = options.iter().position(|option| option == value) else {
+        return;
+    };
+
+    let (down, up) = ui.input_mut(|i| {
+        (
+            i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+            i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+        )
+    });
+    if down {
+        *value = options[(current + 1) % options.len()];
+    } else if up {
+        *value = options[(current + options.len() - 1) % options.len()];
     }
 }
 
-fn calculate_avg_return_time(lent: &[Transaction], returned: &[Transaction]) -> Option<f64> {
-    if lent.is_empty() || returned.is_empty() {
-        return None;
+/// `text` cut to `max_chars` characters, ending in an ellipsis when shortened.
+fn ellipsize(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
     }
+    let short: String = text.chars().take(max_chars - 1).collect();
+    format!("{}…", short.trim_end())
+}
 
-    let mut total_days = 0i64;
-    let mut count = 0;
+fn short_name(name: &str) -> String {
+    ellipsize(name, MAX_NAME_CHARS)
+}
 
-    for ret in returned {
-        if let Some(lent_tx) = lent
-            .iter()
-            .filter(|l| l.datetime <= ret.datetime)
-            .max_by_key(|l| l.datetime)
-        {
-            let days = (ret.datetime.date() - lent_tx.datetime.date()).num_days();
-            total_days += days;
-            count += 1;
+/// Shows the whole name on hover when `short_name` had to cut it.
+fn full_name_on_hover(response: egui::Response, name: &str) -> egui::Response {
+    if name.chars().count() > MAX_NAME_CHARS {
+        response.on_hover_text(name)
+    } else {
+        response
+    }
+}
+
+/// Color swatch, symbol and code for each currency, matching the chart colors.
+fn draw_currency_legend(ui: &mut egui::Ui, settings: &Settings, currencies: &[MoneyType]) {
+    ui.horizontal_wrapped(|ui| {
+        for money_type in currencies {
+            let (rect, _) = ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+            ui.painter()
+                .rect_filled(rect, 3.0, settings.currency_color(*money_type));
+            ui.label(format!(
+                "{} {}",
+                money_type.symbol(),
+                settings.currency_code(*money_type)
+            ));
+            ui.add_space(12.0);
         }
+    });
+}
+
+/// Says how many people a chart left out because their amounts span several currencies.
+fn draw_no_rates_note(ui: &mut egui::Ui, left_out: usize) {
+    if left_out == 0 {
+        return;
     }
+    ui.label(
+        egui::RichText::new(format!(
+            "⚠ {} {} with several currencies left out",
+            left_out,
+            if left_out == 1 { "person" } else { "people" }
+        ))
+        .weak()
+        .small(),
+    )
+    .on_hover_text(NO_RATES_NOTE);
+}
 
-    if count > 0 {
-        Some(total_days as f64 / count as f64)
-    } else {
-        None
+fn draw_sparkline(ui: &mut egui::Ui, series: &[(egui::Color32, Vec<f64>)]) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(200.0, 32.0), egui::Sense::hover());
+    let (min, max) = series
+        .iter()
+        .flat_map(|(_, points)| points)
+        .fold((0.0_f64, 0.0_f64), |(min, max), v| {
+            (min.min(*v), max.max(*v))
+        });
+    let range = (max - min).max(f64::EPSILON);
+    let y = |v: f64| rect.bottom() - ((v - min) / range) as f32 * rect.height();
+
+    let painter = ui.painter_at(rect);
+    painter.hline(
+        rect.x_range(),
+        y(0.0),
+        egui::Stroke::new(1.0, ui.visuals().weak_text_color()),
+    );
+    for (color, points) in series {
+        let step = rect.width() / (points.len() - 1).max(1) as f32;
+        let line: Vec<egui::Pos2> = points
+            .iter()
+            .enumerate()
+            .map(|(i, v)| egui::pos2(rect.left() + i as f32 * step, y(*v)))
+            .collect();
+        painter.add(egui::Shape::line(line, egui::Stroke::new(1.5, *color)));
     }
 }
 
-fn calculate_promise_keeping_rate(
-    lent: &[Transaction],
-    returned: &[Transaction],
-) -> Option<(usize, usize)> {
-    let lent_with_expected: Vec<_> = lent
+fn restore_warning(db: &Database) -> Option<String> {
+    let backup = db.restored_from.as_ref()?;
+    let saved = db
+        .saved_at
+        .map(|at| {
+            format!(
+                ", saved {}",
+                at.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+            )
+        })
+        .unwrap_or_default();
+    Some(format!(
+        "The data file was corrupt and was restored from {}{}. \
+         Anything recorded after that backup was made is missing.",
+        backup, saved
+    ))
+}
+
+fn find_missing_attachments(transactions: &[Transaction]) -> HashSet<String> {
+    transactions
         .iter()
-        .filter(|t| t.expected_return_date.is_some())
-        .collect();
+        .filter_map(|t| t.attachment_path.as_ref())
+        .filter(|path| !Path::new(path).exists())
+        .cloned()
+        .collect()
+}
 
-    if lent_with_expected.is_empty() {
+/// Downscaled preview of an attachment, decoded off the UI thread the first time
+/// its row becomes visible.
+fn load_thumbnail(
+    thumbnails: &mut HashMap<String, Thumbnail>,
+    sender: &Sender<(String, Option<egui::ColorImage>)>,
+    ui: &egui::Ui,
+    path: &str,
+) -> Option<egui::TextureHandle> {
+    match thumbnails.get(path) {
+        Some(Thumbnail::Ready(texture)) => return Some(texture.clone()),
+        Some(_) => return None,
+        None => {}
+    }
+
+    let slot = egui::Rect::from_min_size(ui.cursor().min, egui::Vec2::splat(THUMBNAIL_SIZE));
+    if !ui.is_rect_visible(slot) {
         return None;
     }
 
-    let mut promises_kept = 0;
-    let mut total_promises = 0;
+    thumbnails.insert(path.to_string(), Thumbnail::Loading);
+    let sender = sender.clone();
+    let path = path.to_string();
+    let ctx = ui.ctx().clone();
+    std::thread::spawn(move || {
+        let image = image::open(&path).ok().map(|img| {
+            let size = (THUMBNAIL_SIZE * 2.0) as u32;
+            let img = img.thumbnail(size, size).to_rgba8();
+            let (w, h) = img.dimensions();
+            egui::ColorImage::from_rgba_unmultiplied([w as usize, h as usize], &img.into_raw())
+        });
+        let _ = sender.send((path, image));
+        ctx.request_repaint();
+    });
+
+    None
+}
 
-    for lent_tx in lent_with_expected {
-        if let Some(expected_date) = lent_tx.expected_return_date {
-            total_promises += 1;
+fn parse_amount(input: &str) -> Result<f64, &'static str> {
+    match input.trim().parse::<f64>() {
+        Ok(amount) if amount.is_finite() && amount > 0.0 => Ok(amount),
+        Ok(_) => Err("must be greater than zero"),
+        Err(_) => Err("not a number"),
+    }
+}
 
-            if let Some(return_tx) = returned
-                .iter()
-                .filter(|r| r.datetime >= lent_tx.datetime)
-                .min_by_key(|r| r.datetime)
-            {
-                if return_tx.datetime.date() <= expected_date {
-                    promises_kept += 1;
-                }
-            }
-        }
+/// Like `parse_amount`, but adjustments may also be negative.
+pub(crate) fn parse_direction_amount(
+    direction: Direction,
+    input: &str,
+) -> Result<f64, &'static str> {
+    if direction != Direction::Adjustment {
+        return parse_amount(input);
+    }
+
+    match input.trim().parse::<f64>() {
+        Ok(amount) if amount.is_finite() && amount != 0.0 => Ok(amount),
+        Ok(_) => Err("must not be zero"),
+        Err(_) => Err("not a number"),
     }
+}
 
-    if total_promises > 0 {
-        Some((promises_kept, total_promises))
+fn is_pdf(path: &str) -> bool {
+    PathBuf::from(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("pdf"))
+}
+
+fn attachment_icon(path: &str) -> &'static str {
+    if is_pdf(path) {
+        "📄"
     } else {
-        None
+        "📷"
     }
 }
+
+fn open_command(path: &str) -> std::process::Command {
+    // Not `cmd /C start`: cmd would treat characters like `&` in the file name as syntax.
+    // Explorer wants an absolute path with backslashes.
+    #[cfg(target_os = "windows")]
+    let (mut command, path) = {
+        let path = std::path::PathBuf::from(path.replace('/', "\\"));
+        (
+            std::process::Command::new("explorer"),
+            std::env::current_dir().map_or_else(|_| path.clone(), |dir| dir.join(&path)),
+        )
+    };
+    #[cfg(target_os = "macos")]
+    let mut command = std::process::Command::new("open");
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let mut command = std::process::Command::new("xdg-open");
+
+    command.arg(path);
+    command
+}
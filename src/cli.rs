@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use chrono::{Local, NaiveDate, NaiveTime, Utc};
+
+use crate::{
+    app::parse_direction_amount,
+    database::Database,
+    models::{local_to_utc, Direction, MoneyType, Transaction},
+    server,
+    settings::Settings,
+    stats::calculate_person_stats,
+};
+
+const USAGE: &str = "Usage:
+  banking_app                       launch the GUI
+  banking_app add --person NAME --amount AMOUNT [--currency CODE] [--direction DIRECTION]
+                  [--date YYYY-MM-DD] [--due YYYY-MM-DD]
+                  (currency defaults to GEL, direction to Lent)
+  banking_app list [--person NAME]
+  banking_app balance [--person NAME]
+  banking_app --serve [PORT]";
+
+/// Runs a headless command if one was given, returning the exit code.
+/// Returns `None` when the GUI should start instead.
+pub fn run(args: &[String]) -> Option<i32> {
+    let command = args.get(1)?;
+    let options = &args[2..];
+    if !["add", "list", "balance", "--serve", "help", "--help", "-h"].contains(&command.as_str()) {
+        return None;
+    }
+    attach_console();
+
+    let result = match command.as_str() {
+        "add" => parse_options(options).and_then(|opts| add(&opts)),
+        "list" => parse_options(options).map(|opts| list(&opts)),
+        "balance" => parse_options(options).map(|opts| balance(&opts)),
+        "--serve" => {
+            let port = options
+                .first()
+                .and_then(|port| port.parse().ok())
+                .unwrap_or(server::DEFAULT_PORT);
+            server::serve(port).map_err(|e| e.to_string())
+        }
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => return None,
+    };
+
+    match result {
+        Ok(()) => Some(0),
+        Err(e) => {
+            eprintln!("Error: {}\n\n{}", e, USAGE);
+            Some(1)
+        }
+    }
+}
+
+/// The release build is a GUI-subsystem binary on Windows, which starts without a
+/// console; borrow the one it was launched from so output reaches the terminal.
+#[cfg(windows)]
+fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
+fn matches_person(filter: Option<&String>, name: &str) -> bool {
+    match filter {
+        Some(filter) => name.eq_ignore_ascii_case(filter),
+        None => true,
+    }
+}
+
+fn parse_options(options: &[String]) -> Result<HashMap<String, String>, String> {
+    let mut parsed = HashMap::new();
+    let mut iter = options.iter();
+
+    while let Some(key) = iter.next() {
+        let name = key
+            .strip_prefix("--")
+            .ok_or_else(|| format!("unexpected argument {:?}", key))?;
+        let value = iter
+            .next()
+            .ok_or_else(|| format!("missing value for --{}", name))?;
+        parsed.insert(name.to_string(), value.clone());
+    }
+
+    Ok(parsed)
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("invalid date {:?}, expected YYYY-MM-DD", value))
+}
+
+fn add(opts: &HashMap<String, String>) -> Result<(), String> {
+    let name = opts
+        .get("person")
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+        .ok_or("--person is required")?;
+    let amount = opts.get("amount").ok_or("--amount is required")?;
+    let money_type = opts
+        .get("currency")
+        .map(|code| MoneyType::from_code(code).ok_or(format!("unknown currency {:?}", code)))
+        .transpose()?
+        .unwrap_or(MoneyType::GEL);
+    let direction = opts
+        .get("direction")
+        .map(|name| Direction::from_name(name).ok_or(format!("unknown direction {:?}", name)))
+        .transpose()?
+        .unwrap_or(Direction::Lent);
+    let amount = parse_direction_amount(direction, amount)
+        .map_err(|e| format!("invalid amount {:?}: {}", amount, e))?;
+
+    let datetime = match opts.get("date") {
+        Some(date) => local_to_utc(parse_date(date)?.and_time(NaiveTime::MIN)),
+        None => Utc::now(),
+    };
+
+    let mut db = Database::load();
+    let mut transaction = Transaction::new(
+        db.person_contact(name),
+        amount,
+        money_type,
+        direction,
+        datetime,
+    );
+    if let Some(due) = opts.get("due") {
+        if !matches!(direction, Direction::Lent | Direction::Borrowed) {
+            return Err("--due only applies to lent or borrowed transactions".to_string());
+        }
+        transaction.expected_return_date = Some(parse_date(due)?);
+    }
+
+    db.add_transaction(transaction);
+    db.save().map_err(|e| format!("failed to save: {}", e))?;
+
+    println!(
+        "Added: {:?} {} ({})",
+        direction,
+        Settings::load().format_money_code(money_type, amount),
+        name
+    );
+    Ok(())
+}
+
+fn list(opts: &HashMap<String, String>) {
+    let db = Database::load();
+    let settings = Settings::load();
+    let mut transactions: Vec<(usize, &Transaction)> = db
+        .transactions
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| matches_person(opts.get("person"), &t.person.name))
+        .collect();
+    transactions.sort_by_key(|(_, t)| t.datetime);
+
+    for (idx, t) in transactions {
+        println!(
+            "#{:<5} {}  {:<10} {:>16}  {}",
+            idx + 1,
+            t.datetime.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+            format!("{:?}", t.direction),
+            settings.format_money_code(t.money_type, t.amount),
+            t.person.name
+        );
+    }
+}
+
+fn balance(opts: &HashMap<String, String>) {
+    let db = Database::load();
+    let settings = Settings::load();
+    let person_data = calculate_person_stats(&db.transactions);
+
+    let mut names: Vec<&String> = person_data
+        .keys()
+        .filter(|name| matches_person(opts.get("person"), name))
+        .collect();
+    names.sort();
+
+    for name in names {
+        let stats = &person_data[name];
+        for money_type in settings.currencies() {
+            let Some(c) = stats.by_currency.get(&money_type) else {
+                continue;
+            };
+            let net = c.net();
+            let summary = if net > 0.01 {
+                "owes you"
+            } else if net < -0.01 {
+                "you owe"
+            } else {
+                "square"
+            };
+            println!(
+                "{:<20} {:>16}  {}",
+                name,
+                settings.format_money_code(money_type, net.abs()),
+                summary
+            );
+        }
+    }
+}
@@ -1,30 +1,81 @@
-use std::{fs, path::Path};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    fs,
+    hash::{Hash, Hasher},
+    io::BufReader,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 
-use chrono::Local;
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use crate::models::Transaction;
+use crate::{
+    models::{AuditAction, AuditEntry, Direction, MoneyType, Person, Transaction},
+    settings::{Settings, StorageBackend},
+    storage::{self, Storage},
+};
 
-const DB_FILE: &str = "transactions.json";
+pub const DB_FILE: &str = "transactions.json";
+const ARCHIVE_FILE: &str = "archive.json";
+const ARCHIVE_TEMP_FILE: &str = "archive.json.tmp";
 const BACKUP_DIR: &str = "backups";
 const ATTACHMENTS_DIR: &str = "attachments";
 const MAX_BACKUPS: usize = 50;
 
-#[derive(Default, Serialize, Deserialize)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct Database {
     pub transactions: Vec<Transaction>,
+    #[serde(default)]
+    pub audit_log: Vec<AuditEntry>,
+    #[serde(default)]
+    pub revision: u64,
+    #[serde(default)]
+    pub saved_at: Option<DateTime<Utc>>,
+    /// Backup the data was restored from because the main file couldn't be read.
+    #[serde(skip)]
+    pub restored_from: Option<String>,
+    #[serde(skip)]
+    synced_revision: u64,
+    #[serde(skip)]
+    synced_modified: Option<SystemTime>,
+    /// Positions in `transactions` per person name, kept in sync by the methods
+    /// that add or remove transactions.
+    #[serde(skip)]
+    person_index: HashMap<String, Vec<usize>>,
+    /// Archive contents waiting to be written with the next save, so the rows
+    /// never sit in both files or in neither.
+    #[serde(skip)]
+    pending_archive: Option<Archive>,
+    /// Stored files whose last reference was removed; deleted once that is saved,
+    /// so a reload or an unsaved quit never points at a missing file.
+    #[serde(skip)]
+    released_attachments: Vec<String>,
 }
 
-impl Database {
+/// Settled history moved out of the main file so it isn't loaded on every start.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct Archive {
+    pub transactions: Vec<Transaction>,
+}
+
+impl Archive {
     pub fn load() -> Self {
-        let _ = fs::create_dir_all(ATTACHMENTS_DIR);
+        read_json(ARCHIVE_FILE).unwrap_or_default()
+    }
 
-        if Path::new(DB_FILE).exists() {
-            if let Ok(data) = fs::read_to_string(DB_FILE) {
-                if let Ok(db) = serde_json::from_str(&data) {
-                    return db;
-                }
-            }
+    fn save_to(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn std::error::Error>> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+impl Database {
+    pub fn load() -> Self {
+        let storage = storage::active();
+        if let Some(db) = storage.read() {
+            return db.synced();
         }
 
         if let Some(backup) = Self::get_most_recent_backup() {
@@ -32,42 +83,636 @@ impl Database {
                 "Main database corrupted, attempting to restore from backup: {}",
                 backup
             );
-            if let Ok(data) = fs::read_to_string(&backup) {
-                if let Ok(db) = serde_json::from_str(&data) {
-                    let _ = fs::copy(&backup, DB_FILE);
-                    return db;
-                }
+            if let Some(mut db) = read_json::<Database>(&backup) {
+                let _ = storage.write(&db);
+                db.restored_from = Some(backup);
+                return db.synced();
             }
         }
 
         Database::default()
     }
 
-    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+    fn synced(mut self) -> Self {
+        self.synced_revision = self.revision;
+        self.synced_modified = Self::disk_modified();
+        self.rebuild_person_index();
+        self.assign_missing_ids();
+        self
+    }
+
+    // Files written before transactions had ids deserialize them as 0.
+    fn assign_missing_ids(&mut self) {
+        let next_id = self.next_id();
+        for (t, id) in self
+            .transactions
+            .iter_mut()
+            .filter(|t| t.id == 0)
+            .zip(next_id..)
+        {
+            t.id = id;
+        }
+    }
+
+    pub fn next_id(&self) -> u64 {
+        self.transactions.iter().map(|t| t.id).max().unwrap_or(0) + 1
+    }
+
+    pub fn find_by_id(&self, id: u64) -> Option<(usize, &Transaction)> {
+        self.transactions
+            .iter()
+            .enumerate()
+            .find(|(_, t)| t.id == id)
+    }
+
+    fn rebuild_person_index(&mut self) {
+        self.person_index.clear();
+        for (idx, t) in self.transactions.iter().enumerate() {
+            self.person_index
+                .entry(t.person.name.clone())
+                .or_default()
+                .push(idx);
+        }
+    }
+
+    pub fn person_transactions<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a Transaction> {
+        self.person_index
+            .get(name)
+            .into_iter()
+            .flatten()
+            .map(|idx| &self.transactions[*idx])
+    }
+
+    fn disk_modified() -> Option<SystemTime> {
+        storage::active().modified()
+    }
+
+    fn disk_revision() -> Option<u64> {
+        storage::active().revision()
+    }
+
+    pub fn sync_state(&self) -> (u64, Option<SystemTime>) {
+        (self.synced_revision, self.synced_modified)
+    }
+
+    pub fn mark_synced(&mut self, revision: u64, modified: Option<SystemTime>) {
+        self.revision = self.revision.max(revision);
+        self.synced_revision = revision;
+        self.synced_modified = modified;
+    }
+
+    /// True when another copy of the app (e.g. on a second machine sharing a
+    /// synced folder) wrote the data file after we last read or saved it.
+    pub fn changed_on_disk(&mut self) -> bool {
+        let modified = Self::disk_modified();
+        if modified.is_none() || modified == self.synced_modified {
+            return false;
+        }
+
+        match Self::disk_revision() {
+            Some(revision) if revision != self.synced_revision => true,
+            _ => {
+                self.synced_modified = modified;
+                false
+            }
+        }
+    }
+
+    pub fn save(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let storage = storage::active();
+        let disk_revision = storage.revision();
+        if disk_revision.is_some_and(|revision| revision != self.synced_revision) {
+            return Err(
+                "the data file was changed by another copy of the app since it was loaded".into(),
+            );
+        }
+
+        self.write_to(storage.as_ref(), disk_revision.unwrap_or(0))
+    }
+
+    pub fn save_overwriting(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let storage = storage::active();
+        let disk_revision = storage.revision().unwrap_or(0);
+        self.write_to(storage.as_ref(), disk_revision)
+    }
+
+    fn write_to(
+        &mut self,
+        storage: &dyn Storage,
+        disk_revision: u64,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         fs::create_dir_all(BACKUP_DIR)?;
 
-        if Path::new(DB_FILE).exists() {
+        self.revision = self.revision.max(disk_revision) + 1;
+        self.saved_at = Some(Utc::now());
+
+        // Our own saves are already backed up; only keep a file another copy wrote.
+        if storage.path() == DB_FILE
+            && disk_revision != self.synced_revision
+            && Path::new(DB_FILE).exists()
+        {
             let timestamp = Local::now().format("%Y%m%d_%H%M%S");
-            let backup_file = format!("{}/transactions_backup_{}.json", BACKUP_DIR, timestamp);
+            let backup_file = format!(
+                "{}/transactions_backup_{}_overwritten.json",
+                BACKUP_DIR, timestamp
+            );
             fs::copy(DB_FILE, &backup_file)?;
         }
 
-        let json = serde_json::to_string_pretty(self)?;
-        fs::write(DB_FILE, &json)?;
+        // The archive goes to a side file first and only replaces the real one once
+        // the main file no longer holds the archived rows.
+        if let Some(archive) = &self.pending_archive {
+            archive.save_to(ARCHIVE_TEMP_FILE)?;
+        }
+        if let Err(e) = storage.write(self) {
+            let _ = fs::remove_file(ARCHIVE_TEMP_FILE);
+            return Err(e);
+        }
+        if self.pending_archive.is_some() {
+            fs::rename(ARCHIVE_TEMP_FILE, ARCHIVE_FILE)?;
+        }
 
-        let timestamp = Local::now().format("%Y%m%d_%H%M%S");
-        let backup_file = format!("{}/transactions_backup_{}.json", BACKUP_DIR, timestamp);
-        fs::write(&backup_file, &json)?;
+        let unchanged = Self::get_most_recent_backup()
+            .and_then(|backup| read_json::<Database>(&backup))
+            .is_some_and(|backup| backup.content_hash() == self.content_hash());
+        if !unchanged {
+            let timestamp = Local::now().format("%Y%m%d_%H%M%S");
+            // The revision keeps two saves within the same second from sharing a file.
+            let backup_file = format!(
+                "{}/transactions_backup_{}_r{}.json",
+                BACKUP_DIR, timestamp, self.revision
+            );
+            fs::write(&backup_file, serde_json::to_string_pretty(self)?)?;
+            Self::cleanup_old_backups()?;
+        }
 
-        Self::cleanup_old_backups()?;
+        self.synced_revision = self.revision;
+        self.synced_modified = storage.modified();
 
         Ok(())
     }
 
-    pub fn add_transaction(&mut self, transaction: Transaction) {
+    /// Moves the data to `backend`, which is used from then on. The old file is
+    /// kept in the backups folder.
+    pub fn migrate_to(
+        &mut self,
+        backend: StorageBackend,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let current = storage::active();
+        let target = storage::open(backend);
+        if current.path() == target.path() {
+            storage::select(backend);
+            return Ok(());
+        }
+
+        self.revision += 1;
+        self.saved_at = Some(Utc::now());
+        target.write(self)?;
+
+        let old = Path::new(current.path());
+        if old.exists() {
+            fs::create_dir_all(BACKUP_DIR)?;
+            let timestamp = Local::now().format("%Y%m%d_%H%M%S");
+            let extension = old.extension().and_then(|e| e.to_str()).unwrap_or("json");
+            fs::rename(
+                old,
+                format!(
+                    "{}/transactions_backup_{}_before_{}.{}",
+                    BACKUP_DIR,
+                    timestamp,
+                    backend.label().to_lowercase(),
+                    extension
+                ),
+            )?;
+        }
+
+        storage::select(backend);
+        self.synced_revision = self.revision;
+        self.synced_modified = target.modified();
+        Ok(())
+    }
+
+    pub fn add_transaction(&mut self, mut transaction: Transaction) {
+        if transaction.id == 0 {
+            transaction.id = self.next_id();
+        }
+        self.record_audit(AuditAction::Added, transaction_summary(&transaction));
+        self.person_index
+            .entry(transaction.person.name.clone())
+            .or_default()
+            .push(self.transactions.len());
         self.transactions.push(transaction);
     }
 
+    /// Transactions stored in another data file or backup, or `None` if it can't be read.
+    pub fn read_import(path: &Path) -> Option<Vec<Transaction>> {
+        read_json::<Database>(path).map(|db| db.transactions)
+    }
+
+    /// Adds imported transactions under fresh ids and returns how many were added.
+    /// With `replace`, the current transactions are written to a backup and removed first.
+    pub fn import_transactions(
+        &mut self,
+        imported: Vec<Transaction>,
+        replace: bool,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        if replace {
+            self.write_backup("before_import")?;
+            let removed = self.transactions.len();
+            self.transactions.clear();
+            self.record_audit(
+                AuditAction::Deleted,
+                format!(
+                    "Removed {} transaction(s) to replace them with an import",
+                    removed
+                ),
+            );
+        }
+
+        let first_id = self.next_id();
+        let new_ids: HashMap<u64, u64> = imported
+            .iter()
+            .zip(first_id..)
+            .filter(|(t, _)| t.id != 0)
+            .map(|(t, id)| (t.id, id))
+            .collect();
+
+        let count = imported.len();
+        for (mut t, id) in imported.into_iter().zip(first_id..) {
+            t.id = id;
+            t.linked_to = t.linked_to.and_then(|old| new_ids.get(&old).copied());
+            t.group_id = t.group_id.and_then(|old| new_ids.get(&old).copied());
+            self.transactions.push(t);
+        }
+        self.rebuild_person_index();
+
+        self.record_audit(
+            AuditAction::Added,
+            format!("Imported {} transaction(s)", count),
+        );
+
+        Ok(count)
+    }
+
+    /// The archive as it will be after the next save.
+    pub fn archive(&self) -> Archive {
+        self.pending_archive.clone().unwrap_or_else(Archive::load)
+    }
+
+    /// Call once everything in memory has been saved.
+    pub fn finish_saved(&mut self) {
+        let released = std::mem::take(&mut self.released_attachments);
+        if !released.is_empty() {
+            let archive = self.archive();
+            for path in released {
+                self.remove_if_unreferenced(&path, &archive);
+            }
+        }
+        self.pending_archive = None;
+    }
+
+    /// Moves loans dated before `cutoff` that were fully paid back (by the settings'
+    /// tolerance) or marked settled, together with the returns that settled them, into
+    /// the archive.
+    /// Both files are written by the next save. Returns how many transactions were moved.
+    pub fn archive_settled_before(&mut self, cutoff: NaiveDate, settings: &Settings) -> usize {
+        let mut archived = HashSet::new();
+        let mut ledgers: HashMap<(&str, MoneyType, bool), Vec<usize>> = HashMap::new();
+        for (idx, t) in self.transactions.iter().enumerate() {
+            if t.local_datetime().date() >= cutoff || !t.direction.is_loan_flow() {
+                continue;
+            }
+            // Loans marked settled by hand are done on their own and take no returns.
+            if t.settled && matches!(t.direction, Direction::Lent | Direction::Borrowed) {
+                archived.insert(idx);
+                continue;
+            }
+            let i_lent = matches!(t.direction, Direction::Lent | Direction::Returned);
+            ledgers
+                .entry((t.person.name.as_str(), t.money_type, i_lent))
+                .or_default()
+                .push(idx);
+        }
+
+        for mut indices in ledgers.into_values() {
+            indices.sort_by_key(|idx| self.transactions[*idx].datetime);
+
+            let (mut loaned, mut paid_back) = (0.0, 0.0);
+            let mut settled_prefix = 0;
+            for (n, idx) in indices.iter().enumerate() {
+                let t = &self.transactions[*idx];
+                if matches!(t.direction, Direction::Lent | Direction::Borrowed) {
+                    loaned += t.amount;
+                } else {
+                    paid_back += t.amount;
+                }
+                if paid_back > 0.0 && settings.is_negligible(loaned, loaned - paid_back) {
+                    settled_prefix = n + 1;
+                }
+            }
+            archived.extend(indices.into_iter().take(settled_prefix));
+        }
+
+        if archived.is_empty() {
+            return 0;
+        }
+
+        let mut archive = self.archive();
+        archive.transactions.extend(
+            self.transactions
+                .iter()
+                .enumerate()
+                .filter(|(idx, _)| archived.contains(idx))
+                .map(|(_, t)| t.clone()),
+        );
+        archive.transactions.sort_by_key(|t| t.datetime);
+        self.pending_archive = Some(archive);
+
+        let mut idx = 0;
+        self.transactions.retain(|_| {
+            idx += 1;
+            !archived.contains(&(idx - 1))
+        });
+        self.rebuild_person_index();
+
+        self.record_audit(
+            AuditAction::Archived,
+            format!(
+                "Archived {} settled transaction(s) before {}",
+                archived.len(),
+                cutoff.format("%Y-%m-%d")
+            ),
+        );
+
+        archived.len()
+    }
+
+    /// Removes the given transactions and returns how many were deleted.
+    /// Indices are positions in `transactions` before the call.
+    pub fn delete_transactions(&mut self, indices: &HashSet<usize>) -> usize {
+        let mut removed = Vec::new();
+        let mut idx = 0;
+        self.transactions.retain(|t| {
+            let keep = !indices.contains(&idx);
+            if !keep {
+                removed.push(t.clone());
+            }
+            idx += 1;
+            keep
+        });
+        self.rebuild_person_index();
+
+        for t in &removed {
+            self.record_audit(AuditAction::Deleted, transaction_summary(t));
+            if let Some(path) = &t.attachment_path {
+                self.released_attachments.push(path.clone());
+            }
+        }
+
+        removed.len()
+    }
+
+    /// Adds `tag` to each given transaction that doesn't have it yet and returns how many changed.
+    pub fn tag_transactions(&mut self, indices: &HashSet<usize>, tag: &str) -> usize {
+        let mut tagged = 0;
+        for idx in indices {
+            if let Some(t) = self.transactions.get_mut(*idx) {
+                if !t.tags.iter().any(|existing| existing == tag) {
+                    t.tags.push(tag.to_string());
+                    tagged += 1;
+                }
+            }
+        }
+
+        if tagged > 0 {
+            self.record_audit(
+                AuditAction::Edited,
+                format!("Tagged {} transaction(s) with #{}", tagged, tag),
+            );
+        }
+
+        tagged
+    }
+
+    /// Groups of person names that only differ by case or whitespace, most used name first.
+    pub fn duplicate_people(&self) -> Vec<Vec<String>> {
+        let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+        for name in self.person_index.keys() {
+            let key = name
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .to_lowercase();
+            groups.entry(key).or_default().push(name.clone());
+        }
+
+        let mut duplicates: Vec<Vec<String>> = groups
+            .into_values()
+            .filter(|names| names.len() > 1)
+            .map(|mut names| {
+                names.sort_by(|a, b| {
+                    self.person_index[b]
+                        .len()
+                        .cmp(&self.person_index[a].len())
+                        .then_with(|| a.cmp(b))
+                });
+                names
+            })
+            .collect();
+        duplicates.sort_by_key(|names| names[0].to_lowercase());
+        duplicates
+    }
+
+    /// Renames every transaction of the given people to `canonical` and returns how many changed.
+    pub fn merge_people(&mut self, names: &[String], canonical: &str) -> usize {
+        let mut merged = 0;
+        for t in &mut self.transactions {
+            if t.person.name != canonical && names.contains(&t.person.name) {
+                t.person.name = canonical.to_string();
+                merged += 1;
+            }
+        }
+
+        if merged > 0 {
+            self.rebuild_person_index();
+            self.record_audit(
+                AuditAction::Edited,
+                format!(
+                    "Merged {} into {} ({} transaction(s))",
+                    names
+                        .iter()
+                        .filter(|name| *name != canonical)
+                        .map(|name| format!("{:?}", name))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    canonical,
+                    merged
+                ),
+            );
+        }
+
+        merged
+    }
+
+    pub fn person_contact(&self, name: &str) -> Person {
+        self.person_transactions(name)
+            .last()
+            .map(|t| t.person.clone())
+            .unwrap_or_else(|| Person {
+                name: name.to_string(),
+                phone: None,
+                email: None,
+            })
+    }
+
+    pub fn update_person_contact(&mut self, contact: &Person) {
+        for idx in self.person_index.get(&contact.name).into_iter().flatten() {
+            self.transactions[*idx].person = contact.clone();
+        }
+    }
+
+    pub fn record_audit(&mut self, action: AuditAction, description: String) {
+        self.audit_log.push(AuditEntry {
+            action,
+            description,
+            changed_at: Utc::now(),
+        });
+    }
+
+    /// Replaces a transaction's attachment. The old stored file is deleted after the
+    /// next save if no other transaction still points at it.
+    pub fn set_attachment(&mut self, index: usize, attachment_path: Option<String>) {
+        let Some(t) = self.transactions.get_mut(index) else {
+            return;
+        };
+
+        if let Some(old) = std::mem::replace(&mut t.attachment_path, attachment_path) {
+            self.released_attachments.push(old);
+        }
+    }
+
+    /// Replaces the transaction at `index` and logs which fields changed. An
+    /// attachment that was swapped out is deleted after the next save if nothing
+    /// else uses it.
+    pub fn update_transaction(&mut self, index: usize, updated: Transaction) {
+        let Some(old) = self.transactions.get(index) else {
+            return;
+        };
+
+        let mut changed = Vec::new();
+        if old.person.name != updated.person.name {
+            changed.push("person");
+        }
+        if old.amount != updated.amount {
+            changed.push("amount");
+        }
+        if old.money_type != updated.money_type {
+            changed.push("currency");
+        }
+        if old.direction != updated.direction {
+            changed.push("direction");
+        }
+        if old.datetime != updated.datetime {
+            changed.push("date");
+        }
+        if old.tags != updated.tags {
+            changed.push("tags");
+        }
+        if old.note != updated.note {
+            changed.push("note");
+        }
+        if old.attachment_path != updated.attachment_path {
+            changed.push("attachment");
+        }
+        if changed.is_empty() {
+            return;
+        }
+
+        let description = format!(
+            "#{} ({}) edited: {}",
+            index + 1,
+            updated.person.name,
+            changed.join(", ")
+        );
+        let new_attachment = updated.attachment_path.clone();
+        let old = std::mem::replace(&mut self.transactions[index], updated);
+        if let Some(path) = old
+            .attachment_path
+            .filter(|path| Some(path) != new_attachment.as_ref())
+        {
+            self.released_attachments.push(path);
+        }
+        self.rebuild_person_index();
+        self.record_audit(AuditAction::Edited, description);
+    }
+
+    /// Deletes a stored file right away if nothing points at it, e.g. one copied in
+    /// for a form that was then discarded.
+    pub fn remove_unreferenced_attachment(&self, path: &str) {
+        self.remove_if_unreferenced(path, &self.archive());
+    }
+
+    fn remove_if_unreferenced(&self, path: &str, archive: &Archive) {
+        let referenced = self
+            .transactions
+            .iter()
+            .chain(&archive.transactions)
+            .any(|t| t.attachment_path.as_deref() == Some(path));
+
+        if !referenced && Path::new(path).starts_with(ATTACHMENTS_DIR) {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    /// Files in the attachment folder that neither the data nor the archive points at.
+    pub fn orphaned_attachments(&self, archive: &Archive) -> Vec<PathBuf> {
+        let referenced: HashSet<&Path> = self
+            .transactions
+            .iter()
+            .chain(&archive.transactions)
+            .filter_map(|t| t.attachment_path.as_deref())
+            .map(Path::new)
+            .collect();
+
+        let mut orphaned: Vec<PathBuf> = fs::read_dir(ATTACHMENTS_DIR)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| Path::new(ATTACHMENTS_DIR).join(entry.file_name()))
+            .filter(|path| path.is_file() && !referenced.contains(path.as_path()))
+            .collect();
+        orphaned.sort();
+        orphaned
+    }
+
+    /// Creates the data folders and checks each can be written to, so a read-only
+    /// location shows up at startup rather than when an attachment or backup fails.
+    pub fn check_data_dirs() -> Vec<String> {
+        [
+            (".", "data"),
+            (ATTACHMENTS_DIR, "attachments"),
+            (BACKUP_DIR, "backups"),
+        ]
+        .into_iter()
+        .filter_map(|(dir, label)| {
+            let probe = Path::new(dir).join(".write_test");
+            fs::create_dir_all(dir)
+                .and_then(|_| fs::write(&probe, b""))
+                .and_then(|_| fs::remove_file(&probe))
+                .err()
+                .map(|e| {
+                    let location = fs::canonicalize(dir).unwrap_or_else(|_| PathBuf::from(dir));
+                    format!(
+                        "The {} folder ({}) isn't writable: {}",
+                        label,
+                        location.display(),
+                        e
+                    )
+                })
+        })
+        .collect()
+    }
+
     pub fn copy_attachment_to_storage(
         source_path: &str,
     ) -> Result<String, Box<dyn std::error::Error>> {
@@ -89,35 +734,90 @@ impl Database {
         Ok(dest_path)
     }
 
-    fn get_most_recent_backup() -> Option<String> {
-        if !Path::new(BACKUP_DIR).exists() {
-            return None;
-        }
+    /// Copies the data, the archive and every attachment into a new folder under
+    /// the backups folder and returns its path.
+    pub fn write_bundle(&self, label: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let timestamp = Local::now().format("%Y%m%d_%H%M%S");
+        let dir = Path::new(BACKUP_DIR).join(format!("bundle_{}_{}", timestamp, label));
+        fs::create_dir_all(dir.join(ATTACHMENTS_DIR))?;
 
-        let mut backups: Vec<_> = fs::read_dir(BACKUP_DIR)
-            .ok()?
-            .filter_map(|entry| {
-                let entry = entry.ok()?;
-                let path = entry.path();
-                if path.extension()?.to_str()? == "json" {
-                    Some(path.to_string_lossy().to_string())
-                } else {
-                    None
+        fs::write(dir.join(DB_FILE), serde_json::to_string_pretty(self)?)?;
+        if let Some(archive) = &self.pending_archive {
+            archive.save_to(dir.join(ARCHIVE_FILE))?;
+        } else if Path::new(ARCHIVE_FILE).exists() {
+            fs::copy(ARCHIVE_FILE, dir.join(ARCHIVE_FILE))?;
+        }
+        if let Ok(entries) = fs::read_dir(ATTACHMENTS_DIR) {
+            for entry in entries {
+                let entry = entry?;
+                if entry.file_type()?.is_file() {
+                    fs::copy(
+                        entry.path(),
+                        dir.join(ATTACHMENTS_DIR).join(entry.file_name()),
+                    )?;
                 }
-            })
-            .collect();
+            }
+        }
 
-        backups.sort();
-        backups.reverse();
-        backups.first().cloned()
+        Ok(dir)
+    }
+
+    /// Removes every transaction once a backup bundle has been written, returning
+    /// the bundle's path.
+    pub fn clear_all(&mut self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let bundle = self.write_bundle("before_clear")?;
+
+        let removed = self.transactions.len();
+        self.transactions.clear();
+        self.rebuild_person_index();
+        self.record_audit(
+            AuditAction::Deleted,
+            format!("Cleared all {} transaction(s)", removed),
+        );
+
+        Ok(bundle)
+    }
+
+    fn write_backup(&self, label: &str) -> Result<(), Box<dyn std::error::Error>> {
+        fs::create_dir_all(BACKUP_DIR)?;
+        let timestamp = Local::now().format("%Y%m%d_%H%M%S");
+        let backup_file = format!(
+            "{}/transactions_backup_{}_{}.json",
+            BACKUP_DIR, timestamp, label
+        );
+        fs::write(&backup_file, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Hash of the transactions and audit log, leaving out the save bookkeeping.
+    fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        serde_json::to_string(&(&self.transactions, &self.audit_log))
+            .unwrap_or_default()
+            .hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn get_most_recent_backup() -> Option<String> {
+        Self::list_backups()
+            .first()
+            .map(|(path, _)| path.to_string_lossy().to_string())
     }
 
     fn cleanup_old_backups() -> Result<(), Box<dyn std::error::Error>> {
-        if !Path::new(BACKUP_DIR).exists() {
-            return Ok(());
+        for (path, _) in Self::list_backups().iter().skip(MAX_BACKUPS) {
+            let _ = fs::remove_file(path);
         }
 
-        let mut backups: Vec<_> = fs::read_dir(BACKUP_DIR)?
+        Ok(())
+    }
+
+    fn list_backups() -> Vec<(PathBuf, SystemTime)> {
+        let Ok(entries) = fs::read_dir(BACKUP_DIR) else {
+            return Vec::new();
+        };
+
+        let mut backups: Vec<_> = entries
             .filter_map(|entry| {
                 let entry = entry.ok()?;
                 let path = entry.path();
@@ -131,12 +831,215 @@ impl Database {
             })
             .collect();
 
-        backups.sort_by(|a, b| b.1.cmp(&a.1));
+        backups.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| b.0.cmp(&a.0)));
+        backups
+    }
+}
 
-        for (path, _) in backups.iter().skip(MAX_BACKUPS) {
-            let _ = fs::remove_file(path);
+fn transaction_summary(t: &Transaction) -> String {
+    format!(
+        "{:?} {}{:.2} ({})",
+        t.direction,
+        t.money_type.symbol(),
+        t.amount,
+        t.person.name
+    )
+}
+
+/// Parses straight from a buffered file so large histories never sit in memory
+/// twice (once as text, once as values).
+pub(crate) fn read_json<T: DeserializeOwned>(path: impl AsRef<Path>) -> Option<T> {
+    let file = fs::File::open(path).ok()?;
+    serde_json::from_reader(BufReader::new(file)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    // The data files live in the working directory, which is shared by all tests.
+    static WORKING_DIR: Mutex<()> = Mutex::new(());
+
+    fn in_temp_dir(name: &str, test: impl FnOnce()) {
+        let _guard = WORKING_DIR.lock().unwrap_or_else(|e| e.into_inner());
+        let dir =
+            std::env::temp_dir().join(format!("debt_tracker_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let previous = std::env::current_dir().unwrap();
+        std::env::set_current_dir(&dir).unwrap();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(test));
+
+        std::env::set_current_dir(previous).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        if let Err(panic) = result {
+            std::panic::resume_unwind(panic);
         }
+    }
 
-        Ok(())
+    fn lent(name: &str, amount: f64, attachment: Option<&str>) -> Transaction {
+        let mut t = Transaction::new(
+            Person {
+                name: name.to_string(),
+                phone: None,
+                email: None,
+            },
+            amount,
+            MoneyType::USD,
+            Direction::Lent,
+            Utc::now(),
+        );
+        t.attachment_path = attachment.map(str::to_string);
+        t
+    }
+
+    fn stored_files() -> usize {
+        fs::read_dir(ATTACHMENTS_DIR).map_or(0, |entries| entries.count())
+    }
+
+    fn referenced_files(db: &Database) -> usize {
+        db.transactions
+            .iter()
+            .chain(&db.archive().transactions)
+            .filter_map(|t| t.attachment_path.as_deref())
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    #[test]
+    fn stored_attachments_match_references_after_edits() {
+        in_temp_dir("attachments", || {
+            let shared = format!("{}/shared.png", ATTACHMENTS_DIR);
+            let single = format!("{}/single.png", ATTACHMENTS_DIR);
+            let swapped = format!("{}/swapped.png", ATTACHMENTS_DIR);
+            fs::create_dir_all(ATTACHMENTS_DIR).unwrap();
+            for path in [&shared, &single, &swapped] {
+                fs::write(path, b"receipt").unwrap();
+            }
+
+            let mut db = Database::default();
+            db.add_transaction(lent("Ann", 10.0, Some(&shared)));
+            db.add_transaction(lent("Ann", 20.0, Some(&shared)));
+            db.add_transaction(lent("Bob", 30.0, Some(&single)));
+            db.add_transaction(lent("Cid", 40.0, Some(&swapped)));
+            db.save().unwrap();
+            db.finish_saved();
+            assert_eq!(stored_files(), referenced_files(&db));
+
+            db.delete_transactions(&HashSet::from([2]));
+            db.set_attachment(0, None);
+            let mut edited = db.transactions[2].clone();
+            edited.attachment_path = None;
+            db.update_transaction(2, edited);
+
+            // Nothing is deleted before the edits are saved.
+            assert_eq!(stored_files(), 3);
+
+            db.save().unwrap();
+            db.finish_saved();
+            assert_eq!(stored_files(), referenced_files(&db));
+            assert!(Path::new(&shared).exists());
+
+            db.delete_transactions(&HashSet::from([1]));
+            db.save().unwrap();
+            db.finish_saved();
+            assert_eq!(stored_files(), 0);
+            assert_eq!(referenced_files(&db), 0);
+        });
+    }
+
+    #[test]
+    fn unchanged_saves_share_one_backup() {
+        in_temp_dir("backups", || {
+            let mut db = Database::default();
+            db.add_transaction(lent("Ann", 10.0, None));
+            db.save().unwrap();
+            assert_eq!(Database::list_backups().len(), 1);
+
+            db.save().unwrap();
+            assert_eq!(Database::list_backups().len(), 1);
+
+            db.add_transaction(lent("Bob", 20.0, None));
+            db.save().unwrap();
+            assert_eq!(Database::list_backups().len(), 2);
+
+            db.save().unwrap();
+            assert_eq!(Database::list_backups().len(), 2);
+        });
+    }
+
+    #[test]
+    fn hand_settled_loans_are_archived_without_returns() {
+        let mut db = Database::default();
+        let mut forgiven = lent("Ann", 10.0, None);
+        forgiven.datetime -= chrono::Duration::days(30);
+        forgiven.settled = true;
+        let mut paid = forgiven.clone();
+        paid.settled = false;
+        paid.amount = 20.0;
+        let mut returned = paid.clone();
+        returned.direction = Direction::Returned;
+        db.add_transaction(forgiven);
+        db.add_transaction(paid);
+        db.add_transaction(returned);
+
+        let cutoff = Local::now().date_naive();
+        assert_eq!(db.archive_settled_before(cutoff, &Settings::default()), 3);
+        assert!(db.transactions.is_empty());
+    }
+
+    #[test]
+    fn save_refuses_to_overwrite_another_copy() {
+        in_temp_dir("conflict", || {
+            let mut db = Database::default();
+            db.add_transaction(lent("Ann", 10.0, None));
+            db.save().unwrap();
+
+            let mut other = Database::load();
+            other.add_transaction(lent("Bob", 20.0, None));
+            other.save().unwrap();
+            assert_eq!(storage::active().revision(), Some(other.revision));
+
+            db.add_transaction(lent("Cid", 30.0, None));
+            assert!(db.save().is_err());
+            db.save_overwriting().unwrap();
+            assert_eq!(storage::active().revision(), Some(other.revision + 1));
+        });
+    }
+
+    #[test]
+    fn archived_rows_keep_their_attachment() {
+        in_temp_dir("archived_attachment", || {
+            let shared = format!("{}/split.png", ATTACHMENTS_DIR);
+            fs::create_dir_all(ATTACHMENTS_DIR).unwrap();
+            fs::write(&shared, b"receipt").unwrap();
+
+            let mut db = Database::default();
+            let mut old = lent("Ann", 10.0, Some(&shared));
+            old.datetime -= chrono::Duration::days(30);
+            let mut returned = old.clone();
+            returned.direction = Direction::Returned;
+            returned.attachment_path = None;
+            db.add_transaction(old);
+            db.add_transaction(returned);
+            db.add_transaction(lent("Ann", 20.0, Some(&shared)));
+
+            let cutoff = Local::now().date_naive() - chrono::Duration::days(1);
+            assert_eq!(db.archive_settled_before(cutoff, &Settings::default()), 2);
+            // The archive is only written together with the main file.
+            assert!(!Path::new(ARCHIVE_FILE).exists());
+            db.save().unwrap();
+            db.finish_saved();
+            assert_eq!(Archive::load().transactions.len(), 2);
+            assert_eq!(db.transactions.len(), 1);
+
+            db.delete_transactions(&HashSet::from([0]));
+            db.save().unwrap();
+            db.finish_saved();
+            assert!(Path::new(&shared).exists());
+        });
     }
 }
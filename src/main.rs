@@ -1,14 +1,26 @@
 #![windows_subsystem = "windows"]
 
 mod app;
+mod cli;
 mod database;
 mod models;
+mod report;
+mod saver;
+mod server;
+mod settings;
+mod stats;
+mod storage;
 
 use eframe::egui;
 
 use crate::app::BankingApp;
 
 fn main() -> Result<(), eframe::Error> {
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(code) = cli::run(&args) {
+        std::process::exit(code);
+    }
+
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([900.0, 700.0])
@@ -71,10 +83,41 @@ fn load_icon() -> egui::IconData {
             height,
         }
     } else {
-        egui::IconData {
-            rgba: vec![0, 120, 255, 255].repeat(32 * 32),
-            width: 32,
-            height: 32,
+        fallback_icon()
+    }
+}
+
+/// A gold coin with a dollar sign, drawn when the bundled logo can't be decoded.
+fn fallback_icon() -> egui::IconData {
+    const SIZE: u32 = 32;
+    const DOLLAR: [&str; 9] = [
+        "..#..", ".####", "#.#..", "#.#..", ".###.", "..#.#", "..#.#", "####.", "..#..",
+    ];
+
+    let mut rgba = Vec::with_capacity((SIZE * SIZE * 4) as usize);
+    let center = (SIZE as f32 - 1.0) / 2.0;
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let distance = ((x as f32 - center).powi(2) + (y as f32 - center).powi(2)).sqrt();
+            let (gx, gy) = (x as i32 - 11, y as i32 - 7);
+            let in_glyph = (0..10).contains(&gx)
+                && (0..18).contains(&gy)
+                && DOLLAR[(gy / 2) as usize].as_bytes()[(gx / 2) as usize] == b'#';
+
+            let pixel = if distance > 15.5 {
+                [0, 0, 0, 0]
+            } else if distance > 13.0 || in_glyph {
+                [150, 105, 20, 255]
+            } else {
+                [240, 190, 50, 255]
+            };
+            rgba.extend_from_slice(&pixel);
         }
     }
+
+    egui::IconData {
+        rgba,
+        width: SIZE,
+        height: SIZE,
+    }
 }
@@ -1,10 +1,16 @@
-use chrono::{NaiveDate, NaiveDateTime};
+use std::collections::HashMap;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
 use egui::ahash::HashSet;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct Person {
     pub name: String,
+    #[serde(default)]
+    pub phone: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -18,6 +24,15 @@ pub enum MoneyType {
 }
 
 impl MoneyType {
+    pub const ALL: [MoneyType; 6] = [
+        MoneyType::GEL,
+        MoneyType::USD,
+        MoneyType::EUR,
+        MoneyType::GBP,
+        MoneyType::RUB,
+        MoneyType::Other,
+    ];
+
     pub fn symbol(&self) -> &str {
         match self {
             MoneyType::GEL => "₾",
@@ -28,6 +43,12 @@ impl MoneyType {
             MoneyType::Other => "¤",
         }
     }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        MoneyType::ALL
+            .into_iter()
+            .find(|money_type| format!("{:?}", money_type).eq_ignore_ascii_case(code))
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -36,39 +57,246 @@ pub enum Direction {
     Borrowed,
     Returned,
     Repaid,
+    /// Money that changed hands without being a loan (a gift, a refund).
+    Gift,
+    /// Correction to what someone owes me; the amount may be negative.
+    Adjustment,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 6] = [
+        Direction::Lent,
+        Direction::Borrowed,
+        Direction::Returned,
+        Direction::Repaid,
+        Direction::Gift,
+        Direction::Adjustment,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Direction::ALL
+            .into_iter()
+            .find(|direction| format!("{:?}", direction).eq_ignore_ascii_case(name))
+    }
+
+    /// Whether the transaction takes part in matching loans with their returns.
+    pub fn is_loan_flow(&self) -> bool {
+        !matches!(self, Direction::Gift | Direction::Adjustment)
+    }
+
+    /// Effect of this direction on how much the counterparty owes me.
+    pub fn outstanding_sign(&self) -> f64 {
+        match self {
+            Direction::Lent | Direction::Repaid | Direction::Adjustment => 1.0,
+            Direction::Borrowed | Direction::Returned => -1.0,
+            Direction::Gift => 0.0,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeadlineChange {
     pub old_date: NaiveDate,
     pub new_date: NaiveDate,
-    pub changed_at: NaiveDateTime,
+    #[serde(deserialize_with = "deserialize_utc_or_local")]
+    pub changed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum AuditAction {
+    Added,
+    Edited,
+    Deleted,
+    DeadlineChanged,
+    Archived,
+}
+
+impl AuditAction {
+    pub fn icon(&self) -> &str {
+        match self {
+            AuditAction::Added => "➕",
+            AuditAction::Edited => "✏",
+            AuditAction::Deleted => "🗑",
+            AuditAction::DeadlineChanged => "📝",
+            AuditAction::Archived => "🗄",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub action: AuditAction,
+    pub description: String,
+    #[serde(deserialize_with = "deserialize_utc_or_local")]
+    pub changed_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Transaction {
+    #[serde(default)]
+    pub id: u64,
     pub person: Person,
     pub amount: f64,
     pub money_type: MoneyType,
     pub direction: Direction,
-    pub datetime: NaiveDateTime,
+    #[serde(deserialize_with = "deserialize_utc_or_local")]
+    pub datetime: DateTime<Utc>,
     pub expected_return_date: Option<NaiveDate>,
     pub attachment_path: Option<String>,
     #[serde(default)]
     pub deadline_changes: Vec<DeadlineChange>,
+    #[serde(default)]
+    pub settled: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub note: String,
+    /// Something to do about this loan, listed on the Reminders tab.
+    #[serde(default)]
+    pub reminder: Option<String>,
+    /// Id of the loan this return or repayment was recorded against.
+    #[serde(default)]
+    pub linked_to: Option<u64>,
+    /// Shared by transactions entered together as one event; the id of the first of them.
+    #[serde(default)]
+    pub group_id: Option<u64>,
 }
 
-#[derive(Default)]
-pub struct PersonStats {
+impl Transaction {
+    pub fn new(
+        person: Person,
+        amount: f64,
+        money_type: MoneyType,
+        direction: Direction,
+        datetime: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: 0,
+            person,
+            amount,
+            money_type,
+            direction,
+            datetime,
+            expected_return_date: None,
+            attachment_path: None,
+            deadline_changes: Vec::new(),
+            settled: false,
+            tags: Vec::new(),
+            note: String::new(),
+            reminder: None,
+            linked_to: None,
+            group_id: None,
+        }
+    }
+
+    pub fn local_datetime(&self) -> NaiveDateTime {
+        self.datetime.with_timezone(&Local).naive_local()
+    }
+}
+
+pub fn local_to_utc(naive: NaiveDateTime) -> DateTime<Utc> {
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc.from_utc_datetime(&naive))
+}
+
+// Records written before timestamps were stored in UTC hold naive local times.
+fn deserialize_utc_or_local<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw = String::deserialize(deserializer)?;
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(&raw) {
+        return Ok(datetime.with_timezone(&Utc));
+    }
+
+    raw.parse::<NaiveDateTime>()
+        .map(local_to_utc)
+        .map_err(serde::de::Error::custom)
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct CurrencyStats {
     pub lent: f64,
     pub borrowed: f64,
     pub returned: f64,
     pub repaid: f64,
-    pub outstanding: f64,
+    pub gifts: f64,
+    pub adjustments: f64,
+}
+
+impl CurrencyStats {
+    pub fn owed_to_me(&self) -> f64 {
+        self.lent - self.returned
+    }
+
+    pub fn i_owe(&self) -> f64 {
+        self.borrowed - self.repaid
+    }
+
+    pub fn net(&self) -> f64 {
+        self.owed_to_me() - self.i_owe() + self.adjustments
+    }
+}
+
+/// Per-person figures. Amounts are only kept per currency: with no exchange rates
+/// configured, the totals accessors return `None` when several currencies are involved.
+#[derive(Default)]
+pub struct PersonStats {
     pub lent_transactions: Vec<Transaction>,
     pub return_transactions: Vec<Transaction>,
     pub currencies: HashSet<MoneyType>,
     pub deadline_changes_count: usize,
+    pub by_currency: HashMap<MoneyType, CurrencyStats>,
+}
+
+impl PersonStats {
+    /// The one currency this person's entries are in.
+    pub fn single_currency(&self) -> Option<MoneyType> {
+        let mut currencies = self.by_currency.keys();
+        match (currencies.next(), currencies.next()) {
+            (Some(money_type), None) => Some(*money_type),
+            _ => None,
+        }
+    }
+
+    /// Totals in the person's one currency; `None` when there are several.
+    pub fn totals(&self) -> Option<&CurrencyStats> {
+        self.by_currency.get(&self.single_currency()?)
+    }
+
+    /// Net amount the person owes me, in their one currency.
+    pub fn outstanding(&self) -> Option<f64> {
+        self.totals().map(CurrencyStats::net)
+    }
+
+    /// Share of what was lent that came back, in percent; `None` when nothing was
+    /// lent or the loans are in several currencies.
+    pub fn return_rate(&self) -> Option<f64> {
+        let c = self.totals()?;
+        (c.lent > 0.0).then(|| c.returned / c.lent * 100.0)
+    }
+
+    /// Whether anything was lent, in any currency.
+    pub fn has_lent(&self) -> bool {
+        self.by_currency.values().any(|c| c.lent > 0.0)
+    }
+
+    /// Currencies with returns or repayments but no matching loan, usually a data-entry slip.
+    pub fn mismatched_currencies(&self) -> Vec<MoneyType> {
+        let mut mismatched: Vec<MoneyType> = self
+            .by_currency
+            .iter()
+            .filter(|(_, c)| {
+                (c.returned > 0.0 && c.lent <= 0.0) || (c.repaid > 0.0 && c.borrowed <= 0.0)
+            })
+            .map(|(money_type, _)| *money_type)
+            .collect();
+        mismatched.sort_by_key(|money_type| format!("{:?}", money_type));
+        mismatched
+    }
 }
 
 #[derive(PartialEq, Clone, Copy)]
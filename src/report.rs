@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Local, Utc};
+
+use crate::{
+    models::{Direction, MoneyType, Transaction},
+    settings::Settings,
+    stats::calculate_person_stats,
+};
+
+pub fn weekly_digest(
+    transactions: &[Transaction],
+    settled_amounts: &HashMap<usize, f64>,
+    settings: &Settings,
+    now: DateTime<Utc>,
+) -> String {
+    let since = now - Duration::days(7);
+    let today = now.with_timezone(&Local).date_naive();
+    let week_start = since.with_timezone(&Local).date_naive();
+
+    let mut lines = vec![format!(
+        "Weekly digest {} – {}",
+        week_start.format("%Y-%m-%d"),
+        today.format("%Y-%m-%d")
+    )];
+
+    let mut recent: Vec<&Transaction> = transactions
+        .iter()
+        .filter(|t| t.datetime >= since && t.datetime <= now)
+        .collect();
+    recent.sort_by_key(|t| t.datetime);
+
+    lines.push(String::new());
+    lines.push(format!("New transactions ({}):", recent.len()));
+    for t in &recent {
+        lines.push(format!(
+            "  {} {:?} {} — {}",
+            t.local_datetime().format("%Y-%m-%d"),
+            t.direction,
+            settings.format_money(t.money_type, t.amount),
+            t.person.name
+        ));
+    }
+
+    let settled: Vec<&Transaction> = transactions
+        .iter()
+        .enumerate()
+        .filter(|(idx, t)| {
+            matches!(t.direction, Direction::Lent | Direction::Borrowed)
+                && settled_amounts
+                    .get(idx)
+                    .is_some_and(|settled| settings.is_cleared(t.amount, *settled))
+                && recent.iter().any(|r| {
+                    matches!(r.direction, Direction::Returned | Direction::Repaid)
+                        && r.person.name == t.person.name
+                        && r.money_type == t.money_type
+                })
+        })
+        .map(|(_, t)| t)
+        .collect();
+
+    lines.push(String::new());
+    lines.push(format!("Settled loans ({}):", settled.len()));
+    for t in &settled {
+        lines.push(format!(
+            "  {:?} {} — {} (from {})",
+            t.direction,
+            settings.format_money(t.money_type, t.amount),
+            t.person.name,
+            t.local_datetime().format("%Y-%m-%d")
+        ));
+    }
+
+    let mut missed: Vec<&Transaction> = transactions
+        .iter()
+        .enumerate()
+        .filter(|(idx, t)| {
+            t.expected_return_date
+                .is_some_and(|date| date >= week_start && date < today)
+                && !settings.is_cleared(t.amount, settled_amounts.get(idx).copied().unwrap_or(0.0))
+        })
+        .map(|(_, t)| t)
+        .collect();
+    missed.sort_by_key(|t| t.expected_return_date);
+
+    lines.push(String::new());
+    lines.push(format!("Deadlines passed ({}):", missed.len()));
+    for t in &missed {
+        if let Some(date) = t.expected_return_date {
+            lines.push(format!(
+                "  {} {:?} {} — {}",
+                date.format("%Y-%m-%d"),
+                t.direction,
+                settings.format_money(t.money_type, t.amount),
+                t.person.name
+            ));
+        }
+    }
+
+    lines.join("\n")
+}
+
+/// Who owes whom, per person and currency, for sharing when settling up.
+pub fn reconciliation(
+    transactions: &[Transaction],
+    settings: &Settings,
+    now: DateTime<Utc>,
+) -> String {
+    let mut lines = vec![
+        format!(
+            "Who owes whom — as of {}",
+            now.with_timezone(&Local).format("%Y-%m-%d")
+        ),
+        String::new(),
+    ];
+
+    let person_data = calculate_person_stats(transactions);
+    let mut names: Vec<&String> = person_data.keys().collect();
+    names.sort();
+
+    let mut owed_to_me: HashMap<MoneyType, f64> = HashMap::new();
+    let mut i_owe: HashMap<MoneyType, f64> = HashMap::new();
+    for name in names {
+        let stats = &person_data[name];
+        let open: Vec<(MoneyType, f64)> = settings
+            .currencies()
+            .into_iter()
+            .filter_map(|money_type| {
+                let c = stats.by_currency.get(&money_type)?;
+                let net = c.net();
+                (!settings.is_negligible(c.lent + c.borrowed, net)).then_some((money_type, net))
+            })
+            .collect();
+        if open.is_empty() {
+            continue;
+        }
+
+        lines.push(name.clone());
+        for (money_type, net) in open {
+            let amount = settings.format_money_code(money_type, net.abs());
+            if net > 0.0 {
+                lines.push(format!("  {} owes you {}", name, amount));
+                *owed_to_me.entry(money_type).or_default() += net;
+            } else {
+                lines.push(format!("  You owe {} {}", name, amount));
+                *i_owe.entry(money_type).or_default() -= net;
+            }
+        }
+        lines.push(String::new());
+    }
+
+    if owed_to_me.is_empty() && i_owe.is_empty() {
+        lines.push("Everyone is square.".to_string());
+        return lines.join("\n");
+    }
+
+    lines.push("Totals".to_string());
+    for money_type in settings.currencies() {
+        let owed = owed_to_me.get(&money_type).copied().unwrap_or(0.0);
+        let owe = i_owe.get(&money_type).copied().unwrap_or(0.0);
+        if owed == 0.0 && owe == 0.0 {
+            continue;
+        }
+        lines.push(format!(
+            "  {}: you are owed {}, you owe {}",
+            settings.currency_code(money_type),
+            settings.format_money(money_type, owed),
+            settings.format_money(money_type, owe)
+        ));
+    }
+
+    lines.join("\n")
+}
+
+/// All transactions as CSV, oldest first. With `running_totals`, each row also
+/// carries the balance in its currency and the person's balance in it after that
+/// row, signed like `CurrencyStats::net` (positive means owed to me).
+pub fn csv(transactions: &[Transaction], settings: &Settings, running_totals: bool) -> String {
+    let mut sorted: Vec<&Transaction> = transactions.iter().collect();
+    sorted.sort_by_key(|t| t.datetime);
+
+    let mut header = vec![
+        "id",
+        "date",
+        "person",
+        "direction",
+        "amount",
+        "currency",
+        "expected_return",
+        "settled",
+        "tags",
+        "note",
+        "linked_to",
+    ];
+    if running_totals {
+        header.extend(["running_balance", "person_outstanding"]);
+    }
+
+    let mut lines = vec![header.join(",")];
+    let mut balances: HashMap<MoneyType, f64> = HashMap::new();
+    let mut person_balances: HashMap<(&str, MoneyType), f64> = HashMap::new();
+    for t in sorted {
+        let mut fields = vec![
+            t.id.to_string(),
+            t.local_datetime().format("%Y-%m-%d %H:%M").to_string(),
+            t.person.name.clone(),
+            format!("{:?}", t.direction),
+            format!("{:.2}", t.amount),
+            settings.currency_code(t.money_type),
+            t.expected_return_date
+                .map(|date| date.format("%Y-%m-%d").to_string())
+                .unwrap_or_default(),
+            t.settled.to_string(),
+            t.tags.join(" "),
+            t.note.clone(),
+            t.linked_to.map(|id| id.to_string()).unwrap_or_default(),
+        ];
+        if running_totals {
+            let change = t.direction.outstanding_sign() * t.amount;
+            let balance = balances.entry(t.money_type).or_default();
+            *balance += change;
+            let person_balance = person_balances
+                .entry((t.person.name.as_str(), t.money_type))
+                .or_default();
+            *person_balance += change;
+            fields.push(format!("{:.2}", balance));
+            fields.push(format!("{:.2}", person_balance));
+        }
+
+        let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        lines.push(fields.join(","));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+pub fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Transactions in one currency as a QIF cash account, signed as money coming in (+)
+/// or going out (−). Gifts and adjustments carry no cash direction and are left out.
+pub fn qif(transactions: &[Transaction], money_type: MoneyType) -> String {
+    let mut sorted: Vec<&Transaction> = transactions
+        .iter()
+        .filter(|t| t.money_type == money_type && t.direction.is_loan_flow())
+        .collect();
+    sorted.sort_by_key(|t| t.datetime);
+
+    let mut lines = vec!["!Type:Cash".to_string()];
+    for t in sorted {
+        let mut memo = format!("{:?}", t.direction);
+        for tag in &t.tags {
+            memo.push_str(&format!(" #{}", tag));
+        }
+        if !t.note.is_empty() {
+            memo.push_str(&format!(" {}", t.note.replace('\n', " ")));
+        }
+
+        lines.push(format!("D{}", t.local_datetime().format("%m/%d/%Y")));
+        lines.push(format!(
+            "T{:.2}",
+            -t.direction.outstanding_sign() * t.amount
+        ));
+        lines.push(format!("P{}", t.person.name));
+        lines.push(format!("M{}", memo));
+        if t.settled {
+            lines.push("CX".to_string());
+        }
+        lines.push("^".to_string());
+    }
+
+    lines.join("\n") + "\n"
+}
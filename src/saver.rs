@@ -0,0 +1,110 @@
+use std::{
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+    time::SystemTime,
+};
+
+use crate::database::Database;
+
+struct SaveJob {
+    db: Database,
+    overwrite: bool,
+}
+
+struct SaveOutcome {
+    result: Result<(u64, Option<SystemTime>), String>,
+    jobs: usize,
+}
+
+/// Writes the database on a worker thread so the UI never blocks on disk I/O.
+/// Saves queued while one is running are coalesced into a single write of the latest state.
+pub struct BackgroundSaver {
+    sender: Option<Sender<SaveJob>>,
+    results: Receiver<SaveOutcome>,
+    worker: Option<JoinHandle<()>>,
+    in_flight: usize,
+}
+
+impl BackgroundSaver {
+    pub fn new() -> Self {
+        let (sender, jobs) = mpsc::channel::<SaveJob>();
+        let (results_tx, results) = mpsc::channel();
+
+        let worker = thread::spawn(move || {
+            let mut last_written: Option<(u64, Option<SystemTime>)> = None;
+
+            while let Ok(mut job) = jobs.recv() {
+                let mut count = 1;
+                while let Ok(next) = jobs.try_recv() {
+                    let overwrite = job.overwrite || next.overwrite;
+                    job = next;
+                    job.overwrite = overwrite;
+                    count += 1;
+                }
+
+                if let Some((revision, modified)) = last_written {
+                    if revision > job.db.sync_state().0 {
+                        job.db.mark_synced(revision, modified);
+                    }
+                }
+
+                let saved = if job.overwrite {
+                    job.db.save_overwriting()
+                } else {
+                    job.db.save()
+                };
+
+                let result = match saved {
+                    Ok(()) => {
+                        last_written = Some(job.db.sync_state());
+                        Ok(job.db.sync_state())
+                    }
+                    Err(e) => {
+                        eprintln!("Background save failed: {}", e);
+                        Err(e.to_string())
+                    }
+                };
+
+                let _ = results_tx.send(SaveOutcome {
+                    result,
+                    jobs: count,
+                });
+            }
+        });
+
+        Self {
+            sender: Some(sender),
+            results,
+            worker: Some(worker),
+            in_flight: 0,
+        }
+    }
+
+    pub fn save(&mut self, db: Database, overwrite: bool) {
+        if let Some(sender) = &self.sender {
+            if sender.send(SaveJob { db, overwrite }).is_ok() {
+                self.in_flight += 1;
+            }
+        }
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.in_flight > 0
+    }
+
+    /// Next finished save: the revision and file time now on disk, or the error.
+    pub fn try_result(&mut self) -> Option<Result<(u64, Option<SystemTime>), String>> {
+        let outcome = self.results.try_recv().ok()?;
+        self.in_flight = self.in_flight.saturating_sub(outcome.jobs);
+        Some(outcome.result)
+    }
+}
+
+impl Drop for BackgroundSaver {
+    fn drop(&mut self) {
+        self.sender = None;
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
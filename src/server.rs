@@ -0,0 +1,178 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::Path,
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+    database::Database,
+    models::{MoneyType, PersonStats},
+    stats::{calculate_person_stats, calculate_reliability_score},
+    storage,
+};
+
+pub const DEFAULT_PORT: u16 = 8787;
+
+/// Connections are served one at a time, so a client that stalls must not hold the rest up.
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub fn serve(port: u16) -> Result<(), Box<dyn std::error::Error>> {
+    let listener = TcpListener::bind(("127.0.0.1", port)).map_err(|e| {
+        format!(
+            "Could not listen on 127.0.0.1:{} ({}). Is another instance already running?",
+            port, e
+        )
+    })?;
+
+    println!("Serving read-only balances on http://127.0.0.1:{}", port);
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = handle_connection(stream) {
+                    eprintln!("Request failed: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Connection failed: {}", e),
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_connection(mut stream: TcpStream) -> Result<(), Box<dyn std::error::Error>> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+
+    let (status, body) = if method != "GET" {
+        (
+            "405 Method Not Allowed",
+            json!({ "error": "only GET is supported" }),
+        )
+    } else {
+        route(path)
+    };
+
+    let body = serde_json::to_string_pretty(&body)?;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+
+    Ok(())
+}
+
+fn route(path: &str) -> (&'static str, Value) {
+    // Read only: `Database::load` would restore a backup over a file caught mid-save.
+    let store = storage::active();
+    let db = if Path::new(store.path()).exists() {
+        store.read()
+    } else {
+        // Nothing saved yet: no one has a balance.
+        Some(Database::default())
+    };
+    let Some(db) = db else {
+        return (
+            "503 Service Unavailable",
+            json!({ "error": "the data file could not be read, try again" }),
+        );
+    };
+    let person_data = calculate_person_stats(&db.transactions);
+
+    if path == "/people" {
+        let mut names: Vec<&String> = person_data.keys().collect();
+        names.sort();
+        let people: Vec<Value> = names
+            .into_iter()
+            .map(|name| person_json(name, &person_data[name]))
+            .collect();
+        return ("200 OK", Value::Array(people));
+    }
+
+    if let Some(encoded) = path.strip_prefix("/person/") {
+        let name = percent_decode(encoded);
+        return match person_data.get(&name) {
+            Some(stats) => ("200 OK", person_json(&name, stats)),
+            None => (
+                "404 Not Found",
+                json!({ "error": format!("no person named {:?}", name) }),
+            ),
+        };
+    }
+
+    ("404 Not Found", json!({ "error": "unknown endpoint" }))
+}
+
+fn person_json(name: &str, stats: &PersonStats) -> Value {
+    let currencies: serde_json::Map<String, Value> = MoneyType::ALL
+        .iter()
+        .filter_map(|money_type| {
+            let c = stats.by_currency.get(money_type)?;
+            Some((
+                format!("{:?}", money_type),
+                json!({
+                    "lent": c.lent,
+                    "borrowed": c.borrowed,
+                    "returned": c.returned,
+                    "repaid": c.repaid,
+                    "gifts": c.gifts,
+                    "owed_to_me": c.owed_to_me(),
+                    "i_owe": c.i_owe(),
+                    "net": c.net(),
+                }),
+            ))
+        })
+        .collect();
+
+    json!({
+        "name": name,
+        // Null when the person has several currencies: no exchange rates are configured.
+        "outstanding": stats.outstanding(),
+        "lent": stats.totals().map(|c| c.lent),
+        "borrowed": stats.totals().map(|c| c.borrowed),
+        "returned": stats.totals().map(|c| c.returned),
+        "repaid": stats.totals().map(|c| c.repaid),
+        "reliability": calculate_reliability_score(stats),
+        "currencies": currencies,
+    })
+}
+
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+                match u8::from_str_radix(hex, 16) {
+                    Ok(byte) => {
+                        decoded.push(byte);
+                        i += 3;
+                        continue;
+                    }
+                    Err(_) => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
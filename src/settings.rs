@@ -0,0 +1,382 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::Path,
+};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{models::MoneyType, storage};
+
+const SETTINGS_FILE: &str = "settings.json";
+const PASSCODE_ROUNDS: u32 = 100_000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum SymbolPosition {
+    Before,
+    After,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum SaveMode {
+    Immediate,
+    Debounced,
+    Manual,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum StorageBackend {
+    Json,
+    Sqlite,
+}
+
+impl StorageBackend {
+    pub const ALL: [StorageBackend; 2] = [StorageBackend::Json, StorageBackend::Sqlite];
+
+    pub fn label(&self) -> &str {
+        match self {
+            StorageBackend::Json => "JSON",
+            StorageBackend::Sqlite => "SQLite",
+        }
+    }
+
+    /// SQLite needs a build with the `sqlite` feature.
+    pub fn is_available(&self) -> bool {
+        *self == StorageBackend::Json || cfg!(feature = "sqlite")
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum AnalysisSection {
+    Spotlight,
+    Summary,
+    NetBalance,
+    Timeline,
+    Charts,
+    PromiseKeeping,
+    Tags,
+    People,
+}
+
+impl AnalysisSection {
+    pub const ALL: [AnalysisSection; 8] = [
+        AnalysisSection::Spotlight,
+        AnalysisSection::Summary,
+        AnalysisSection::NetBalance,
+        AnalysisSection::Timeline,
+        AnalysisSection::Charts,
+        AnalysisSection::PromiseKeeping,
+        AnalysisSection::Tags,
+        AnalysisSection::People,
+    ];
+
+    pub fn label(&self) -> &str {
+        match self {
+            AnalysisSection::Spotlight => "🔦 Spotlight",
+            AnalysisSection::Summary => "💸 Totals",
+            AnalysisSection::NetBalance => "💼 Net balance",
+            AnalysisSection::Timeline => "📊 Balance timeline",
+            AnalysisSection::Charts => "📈 Outstanding & return rate",
+            AnalysisSection::PromiseKeeping => "🤞 Promise keeping",
+            AnalysisSection::Tags => "🏷 Outstanding by tag",
+            AnalysisSection::People => "👥 Individual statistics",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum StartupTab {
+    AddTransaction,
+    Analysis,
+    History,
+    Reminders,
+    Settings,
+    LastUsed,
+}
+
+impl StartupTab {
+    pub const ALL: [StartupTab; 6] = [
+        StartupTab::AddTransaction,
+        StartupTab::Analysis,
+        StartupTab::History,
+        StartupTab::Reminders,
+        StartupTab::Settings,
+        StartupTab::LastUsed,
+    ];
+
+    pub fn label(&self) -> &str {
+        match self {
+            StartupTab::AddTransaction => "➕ Add Transaction",
+            StartupTab::Analysis => "📊 Analysis",
+            StartupTab::History => "📜 History",
+            StartupTab::Reminders => "🔔 Reminders",
+            StartupTab::Settings => "⚙ Settings",
+            StartupTab::LastUsed => "🕘 Last used",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub auto_lock_enabled: bool,
+    pub auto_lock_minutes: u32,
+    /// Salt and salted SHA-256 of the unlock passcode, as `salt$hash` in hex.
+    pub passcode_hash: Option<String>,
+    pub symbol_position: SymbolPosition,
+    pub currency_colors: HashMap<MoneyType, [u8; 3]>,
+    /// Order currencies are listed in, most used first.
+    pub currency_order: Vec<MoneyType>,
+    /// Decimal places per currency; 2 for any currency not listed.
+    pub decimal_places: HashMap<MoneyType, usize>,
+    /// Code shown for `MoneyType::Other`; "Other" when empty.
+    pub other_currency_code: String,
+    pub ui_scale: f32,
+    pub high_contrast: bool,
+    pub save_mode: SaveMode,
+    pub autosave_secs: u32,
+    /// Where the data is stored; change it through `Database::migrate_to`.
+    pub storage_backend: StorageBackend,
+    pub hidden_timeline_series: BTreeSet<String>,
+    /// People shown first on the Analysis tab, whatever the sort.
+    pub pinned_people: BTreeSet<String>,
+    /// Leftover amount below which a loan counts as paid back (default 0.01).
+    pub cleared_tolerance: f64,
+    /// Same as `cleared_tolerance` but relative to the loan, in percent (default 0).
+    pub cleared_tolerance_percent: f64,
+    pub large_amount_warning: bool,
+    pub large_amount_threshold: f64,
+    /// Warn on the Analysis tab when outstanding loans pass these limits (per currency).
+    pub exposure_warning: bool,
+    pub exposure_limit_total: f64,
+    pub exposure_limit_person: f64,
+    /// Order and visibility of the Analysis tab sections.
+    pub analysis_layout: Vec<(AnalysisSection, bool)>,
+    /// Tab the app opens on; `LastUsed` reopens `last_tab`.
+    pub startup_tab: StartupTab,
+    pub last_tab: StartupTab,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            auto_lock_enabled: false,
+            auto_lock_minutes: 5,
+            passcode_hash: None,
+            symbol_position: SymbolPosition::Before,
+            currency_colors: HashMap::new(),
+            currency_order: MoneyType::ALL.to_vec(),
+            decimal_places: HashMap::new(),
+            other_currency_code: String::new(),
+            ui_scale: 1.0,
+            high_contrast: false,
+            save_mode: SaveMode::Immediate,
+            autosave_secs: 10,
+            storage_backend: storage::detect(),
+            hidden_timeline_series: BTreeSet::new(),
+            pinned_people: BTreeSet::new(),
+            cleared_tolerance: 0.01,
+            cleared_tolerance_percent: 0.0,
+            large_amount_warning: false,
+            large_amount_threshold: 10_000.0,
+            exposure_warning: false,
+            exposure_limit_total: 50_000.0,
+            exposure_limit_person: 5_000.0,
+            analysis_layout: AnalysisSection::ALL.iter().map(|s| (*s, true)).collect(),
+            startup_tab: StartupTab::AddTransaction,
+            last_tab: StartupTab::AddTransaction,
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        if Path::new(SETTINGS_FILE).exists() {
+            if let Ok(data) = fs::read_to_string(SETTINGS_FILE) {
+                if let Ok(settings) = serde_json::from_str(&data) {
+                    return settings;
+                }
+            }
+        }
+
+        Settings::default()
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(SETTINGS_FILE, json)?;
+        Ok(())
+    }
+
+    /// Locking needs a passcode; without one the lock screen would protect nothing.
+    pub fn auto_lock_after_secs(&self) -> Option<f64> {
+        if self.auto_lock_enabled && self.auto_lock_minutes > 0 && self.passcode_hash.is_some() {
+            Some(self.auto_lock_minutes as f64 * 60.0)
+        } else {
+            None
+        }
+    }
+
+    pub fn set_passcode(&mut self, passcode: &str) {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let salt =
+            to_hex(&Sha256::digest(format!("{}:{}", nanos, std::process::id())))[..32].to_string();
+        let hash = hash_passcode(&salt, passcode);
+        self.passcode_hash = Some(format!("{}${}", salt, hash));
+    }
+
+    pub fn check_passcode(&self, passcode: &str) -> bool {
+        self.passcode_hash
+            .as_deref()
+            .and_then(|stored| stored.split_once('$'))
+            .is_some_and(|(salt, hash)| hash_passcode(salt, passcode) == hash)
+    }
+
+    /// Whether `settled` covers `amount` up to the larger of the two tolerances.
+    pub fn is_cleared(&self, amount: f64, settled: f64) -> bool {
+        let tolerance = self
+            .cleared_tolerance
+            .max(amount * self.cleared_tolerance_percent / 100.0);
+        settled > 0.0 && amount - settled < tolerance + f64::EPSILON
+    }
+
+    /// Whether `balance` is close enough to zero to count as square, using the same
+    /// tolerances as `is_cleared` with `amount` as the base for the percentage.
+    pub fn is_negligible(&self, amount: f64, balance: f64) -> bool {
+        let tolerance = self
+            .cleared_tolerance
+            .max(amount * self.cleared_tolerance_percent / 100.0);
+        balance.abs() < tolerance + f64::EPSILON
+    }
+
+    /// `currency_order` with any currencies it doesn't mention appended.
+    pub fn currencies(&self) -> Vec<MoneyType> {
+        let mut currencies: Vec<MoneyType> = Vec::new();
+        for money_type in self.currency_order.iter().chain(&MoneyType::ALL) {
+            if !currencies.contains(money_type) {
+                currencies.push(*money_type);
+            }
+        }
+        currencies
+    }
+
+    /// `analysis_layout` with any sections it doesn't mention appended as visible.
+    pub fn analysis_sections(&self) -> Vec<(AnalysisSection, bool)> {
+        let mut sections = self.analysis_layout.clone();
+        for section in AnalysisSection::ALL {
+            if !sections.iter().any(|(s, _)| *s == section) {
+                sections.push((section, true));
+            }
+        }
+        sections
+    }
+
+    pub fn is_large_amount(&self, amount: f64) -> bool {
+        self.large_amount_warning && amount > self.large_amount_threshold
+    }
+
+    pub fn currency_color(&self, money_type: MoneyType) -> egui::Color32 {
+        let [r, g, b] = self
+            .currency_colors
+            .get(&money_type)
+            .copied()
+            .unwrap_or_else(|| default_currency_color(money_type));
+        egui::Color32::from_rgb(r, g, b)
+    }
+
+    /// Color for a balance from my point of view: positive means they owe me.
+    pub fn balance_color(&self, value: f64) -> egui::Color32 {
+        if value > 0.0 {
+            if self.high_contrast {
+                egui::Color32::from_rgb(230, 159, 0)
+            } else {
+                egui::Color32::from_rgb(255, 130, 130)
+            }
+        } else if value < 0.0 {
+            if self.high_contrast {
+                egui::Color32::from_rgb(86, 180, 233)
+            } else {
+                egui::Color32::from_rgb(130, 220, 130)
+            }
+        } else {
+            egui::Color32::GRAY
+        }
+    }
+
+    /// Like `format_money`, but with an explicit +/− sign in high-contrast mode.
+    pub fn format_balance(&self, money_type: MoneyType, value: f64) -> String {
+        if !self.high_contrast || value == 0.0 {
+            return self.format_money(money_type, value);
+        }
+
+        let sign = if value > 0.0 { "+" } else { "−" };
+        format!("{}{}", sign, self.format_money(money_type, value.abs()))
+    }
+
+    /// Currency code for display, with the custom code for `MoneyType::Other`.
+    pub fn currency_code(&self, money_type: MoneyType) -> String {
+        let custom = self.other_currency_code.trim();
+        if money_type == MoneyType::Other && !custom.is_empty() {
+            custom.to_string()
+        } else {
+            format!("{:?}", money_type)
+        }
+    }
+
+    /// `format_money` followed by the ISO code, since symbols like `¤` or `$` are ambiguous.
+    pub fn format_money_code(&self, money_type: MoneyType, amount: f64) -> String {
+        format!(
+            "{} {}",
+            self.format_money(money_type, amount),
+            self.currency_code(money_type)
+        )
+    }
+
+    pub fn format_balance_code(&self, money_type: MoneyType, value: f64) -> String {
+        format!(
+            "{} {}",
+            self.format_balance(money_type, value),
+            self.currency_code(money_type)
+        )
+    }
+
+    pub fn decimals(&self, money_type: MoneyType) -> usize {
+        self.decimal_places.get(&money_type).copied().unwrap_or(2)
+    }
+
+    pub fn format_money(&self, money_type: MoneyType, amount: f64) -> String {
+        let decimals = self.decimals(money_type);
+        match self.symbol_position {
+            SymbolPosition::Before => format!("{}{:.*}", money_type.symbol(), decimals, amount),
+            SymbolPosition::After => format!("{:.*} {}", decimals, amount, money_type.symbol()),
+        }
+    }
+}
+
+fn hash_passcode(salt: &str, passcode: &str) -> String {
+    let mut digest = Sha256::digest(format!("{}{}", salt, passcode));
+    for _ in 1..PASSCODE_ROUNDS {
+        digest = Sha256::digest(digest);
+    }
+    to_hex(&digest)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn default_currency_color(money_type: MoneyType) -> [u8; 3] {
+    match money_type {
+        MoneyType::GEL => [255, 100, 100],
+        MoneyType::USD => [100, 150, 255],
+        MoneyType::EUR => [100, 220, 100],
+        MoneyType::GBP => [255, 180, 50],
+        MoneyType::RUB => [200, 100, 255],
+        MoneyType::Other => [160, 160, 160],
+    }
+}
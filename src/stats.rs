@@ -0,0 +1,365 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+
+use crate::models::{CurrencyStats, Direction, MoneyType, PersonStats, Transaction};
+
+pub fn calculate_person_stats(transactions: &[Transaction]) -> HashMap<String, PersonStats> {
+    let mut person_data: HashMap<String, PersonStats> = HashMap::new();
+
+    for t in transactions {
+        let stats = person_data.entry(t.person.name.clone()).or_default();
+
+        stats.currencies.insert(t.money_type);
+
+        stats.deadline_changes_count += t.deadline_changes.len();
+
+        let currency_stats = stats.by_currency.entry(t.money_type).or_default();
+        match t.direction {
+            Direction::Lent => currency_stats.lent += t.amount,
+            Direction::Borrowed => currency_stats.borrowed += t.amount,
+            Direction::Returned => currency_stats.returned += t.amount,
+            Direction::Repaid => currency_stats.repaid += t.amount,
+            Direction::Gift => currency_stats.gifts += t.amount,
+            Direction::Adjustment => currency_stats.adjustments += t.amount,
+        }
+
+        match t.direction {
+            Direction::Lent => stats.lent_transactions.push(t.clone()),
+            Direction::Returned => stats.return_transactions.push(t.clone()),
+            _ => {}
+        }
+    }
+
+    person_data
+}
+
+pub fn calculate_currency_totals(
+    transactions: &[Transaction],
+) -> HashMap<MoneyType, CurrencyStats> {
+    let mut totals: HashMap<MoneyType, CurrencyStats> = HashMap::new();
+
+    for t in transactions {
+        let currency_stats = totals.entry(t.money_type).or_default();
+        match t.direction {
+            Direction::Lent => currency_stats.lent += t.amount,
+            Direction::Borrowed => currency_stats.borrowed += t.amount,
+            Direction::Returned => currency_stats.returned += t.amount,
+            Direction::Repaid => currency_stats.repaid += t.amount,
+            Direction::Gift => currency_stats.gifts += t.amount,
+            Direction::Adjustment => currency_stats.adjustments += t.amount,
+        }
+    }
+
+    totals
+}
+
+/// Combined 0–100 reliability score for someone I have lent to.
+///
+/// Weighted average of the available components, each scaled to 0..=1:
+/// - return rate (40%): `returned / lent`, capped at 1
+/// - promises kept (30%): share of loans with a deadline returned on time
+/// - return speed (20%): 1 when the average return takes ≤ 7 days, falling linearly to 0 at 90 days
+/// - deadline discipline (10%): 1 minus 0.25 per deadline change, floored at 0
+///
+/// Components that cannot be computed (no deadlines, no returns, a return rate across
+/// several currencies) are left out and the remaining weights are rescaled. Returns
+/// `None` when nothing was lent.
+pub fn calculate_reliability_score(stats: &PersonStats) -> Option<f64> {
+    if !stats.has_lent() {
+        return None;
+    }
+
+    let mut components = Vec::new();
+
+    if let Some(rate) = stats.return_rate() {
+        components.push((0.4, (rate / 100.0).min(1.0)));
+    }
+
+    if let Some((kept, total)) =
+        calculate_promise_keeping_rate(&stats.lent_transactions, &stats.return_transactions)
+    {
+        components.push((0.3, kept as f64 / total as f64));
+    }
+
+    if let Some(avg_days) =
+        calculate_avg_return_time(&stats.lent_transactions, &stats.return_transactions)
+    {
+        components.push((
+            0.2,
+            (1.0 - (avg_days - 7.0).max(0.0) / 83.0).clamp(0.0, 1.0),
+        ));
+    }
+
+    components.push((
+        0.1,
+        (1.0 - 0.25 * stats.deadline_changes_count as f64).max(0.0),
+    ));
+
+    let total_weight: f64 = components.iter().map(|(weight, _)| weight).sum();
+    let weighted: f64 = components
+        .iter()
+        .map(|(weight, value)| weight * value)
+        .sum();
+
+    Some(weighted / total_weight * 100.0)
+}
+
+pub fn calculate_avg_return_time(lent: &[Transaction], returned: &[Transaction]) -> Option<f64> {
+    if lent.is_empty() || returned.is_empty() {
+        return None;
+    }
+
+    let mut total_days = 0i64;
+    let mut count = 0;
+
+    for ret in returned {
+        if let Some(lent_tx) = lent
+            .iter()
+            .filter(|l| l.datetime <= ret.datetime)
+            .max_by_key(|l| l.datetime)
+        {
+            let days = (ret.local_datetime().date() - lent_tx.local_datetime().date()).num_days();
+            total_days += days;
+            count += 1;
+        }
+    }
+
+    if count > 0 {
+        Some(total_days as f64 / count as f64)
+    } else {
+        None
+    }
+}
+
+/// Each loan with a deadline, its expected date, and the date of the first return
+/// made on or after the loan (`None` if nothing was returned since).
+pub fn match_deadline_returns<'a>(
+    lent: &'a [Transaction],
+    returned: &[Transaction],
+) -> Vec<(&'a Transaction, NaiveDate, Option<NaiveDate>)> {
+    lent.iter()
+        .filter_map(|lent_tx| {
+            let expected_date = lent_tx.expected_return_date?;
+            let returned_on = returned
+                .iter()
+                .filter(|r| r.datetime >= lent_tx.datetime)
+                .min_by_key(|r| r.datetime)
+                .map(|r| r.local_datetime().date());
+            Some((lent_tx, expected_date, returned_on))
+        })
+        .collect()
+}
+
+pub fn calculate_promise_keeping_rate(
+    lent: &[Transaction],
+    returned: &[Transaction],
+) -> Option<(usize, usize)> {
+    let matches = match_deadline_returns(lent, returned);
+
+    if matches.is_empty() {
+        return None;
+    }
+
+    let promises_kept = matches
+        .iter()
+        .filter(|(_, expected_date, returned_on)| {
+            returned_on.is_some_and(|date| date <= *expected_date)
+        })
+        .count();
+
+    Some((promises_kept, matches.len()))
+}
+
+/// Average deadline shift in days across all changes (positive means pushed out),
+/// together with how many changes moved the deadline later and earlier.
+pub fn calculate_deadline_shift<'a>(
+    transactions: impl IntoIterator<Item = &'a Transaction>,
+) -> Option<(f64, usize, usize)> {
+    let shifts: Vec<i64> = transactions
+        .into_iter()
+        .flat_map(|t| &t.deadline_changes)
+        .map(|change| (change.new_date - change.old_date).num_days())
+        .collect();
+
+    if shifts.is_empty() {
+        return None;
+    }
+
+    let later = shifts.iter().filter(|days| **days > 0).count();
+    let earlier = shifts.iter().filter(|days| **days < 0).count();
+    let avg = shifts.iter().sum::<i64>() as f64 / shifts.len() as f64;
+
+    Some((avg, later, earlier))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::*;
+    use crate::models::{DeadlineChange, Person};
+
+    fn tx(direction: Direction, amount: f64, money_type: MoneyType, day: i64) -> Transaction {
+        let datetime = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap() + Duration::days(day);
+        Transaction::new(
+            Person {
+                name: "Ann".to_string(),
+                phone: None,
+                email: None,
+            },
+            amount,
+            money_type,
+            direction,
+            datetime,
+        )
+    }
+
+    fn score(transactions: &[Transaction]) -> Option<f64> {
+        calculate_reliability_score(&calculate_person_stats(transactions)["Ann"])
+    }
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.expect("a score");
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn no_score_without_loans() {
+        assert_eq!(
+            score(&[tx(Direction::Borrowed, 50.0, MoneyType::USD, 0)]),
+            None
+        );
+    }
+
+    #[test]
+    fn nothing_returned_keeps_only_rate_and_discipline() {
+        // rate 0 (0.4) and discipline 1 (0.1), rescaled over 0.5
+        assert_close(
+            score(&[tx(Direction::Lent, 100.0, MoneyType::USD, 0)]),
+            20.0,
+        );
+    }
+
+    #[test]
+    fn prompt_full_return_on_time_scores_full_marks() {
+        let mut loan = tx(Direction::Lent, 100.0, MoneyType::USD, 0);
+        loan.expected_return_date = Some(NaiveDate::from_ymd_opt(2024, 1, 10).unwrap());
+        let returned = tx(Direction::Returned, 100.0, MoneyType::USD, 5);
+
+        assert_close(score(&[loan, returned]), 100.0);
+    }
+
+    #[test]
+    fn return_rate_is_capped_at_one() {
+        let loan = tx(Direction::Lent, 100.0, MoneyType::USD, 0);
+        let returned = tx(Direction::Returned, 150.0, MoneyType::USD, 1);
+
+        assert_close(score(&[loan, returned]), 100.0);
+    }
+
+    #[test]
+    fn slow_partial_return_scales_rate_and_speed() {
+        // rate 0.5 (0.4), speed 1 - 41/83 after 48 days (0.2), discipline 1 (0.1)
+        let loan = tx(Direction::Lent, 100.0, MoneyType::USD, 0);
+        let returned = tx(Direction::Returned, 50.0, MoneyType::USD, 48);
+
+        let expected = (0.4 * 0.5 + 0.2 * (1.0 - 41.0 / 83.0) + 0.1) / 0.7 * 100.0;
+        assert_close(score(&[loan, returned]), expected);
+    }
+
+    #[test]
+    fn late_return_and_moved_deadlines_lower_the_score() {
+        // rate 1 (0.4), promise 0 (0.3), speed 1 - 13/83 after 20 days (0.2),
+        // discipline 1 - 2 * 0.25 (0.1)
+        let mut loan = tx(Direction::Lent, 100.0, MoneyType::USD, 0);
+        let deadline = NaiveDate::from_ymd_opt(2024, 1, 11).unwrap();
+        loan.expected_return_date = Some(deadline);
+        loan.deadline_changes = vec![
+            DeadlineChange {
+                old_date: deadline - Duration::days(5),
+                new_date: deadline - Duration::days(2),
+                changed_at: loan.datetime,
+            },
+            DeadlineChange {
+                old_date: deadline - Duration::days(2),
+                new_date: deadline,
+                changed_at: loan.datetime,
+            },
+        ];
+        let returned = tx(Direction::Returned, 100.0, MoneyType::USD, 20);
+
+        let expected = 0.4 + 0.2 * (1.0 - 13.0 / 83.0) + 0.1 * 0.5;
+        assert_close(score(&[loan, returned]), expected * 100.0);
+    }
+
+    #[test]
+    fn discipline_is_floored_at_zero() {
+        let mut loan = tx(Direction::Lent, 100.0, MoneyType::USD, 0);
+        let change = DeadlineChange {
+            old_date: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
+            new_date: NaiveDate::from_ymd_opt(2024, 1, 6).unwrap(),
+            changed_at: loan.datetime,
+        };
+        loan.deadline_changes = vec![change; 6];
+
+        // rate 0 (0.4) and discipline 0 (0.1)
+        assert_close(score(&[loan]), 0.0);
+    }
+
+    #[test]
+    fn return_rate_is_left_out_across_currencies() {
+        // Only speed 1 (0.2) and discipline 1 (0.1) remain.
+        let loan = tx(Direction::Lent, 100.0, MoneyType::USD, 0);
+        let returned = tx(Direction::Returned, 10.0, MoneyType::EUR, 1);
+
+        assert_close(score(&[loan, returned]), 100.0);
+    }
+
+    #[test]
+    fn currency_totals_are_kept_per_currency() {
+        let transactions = [
+            tx(Direction::Lent, 100.0, MoneyType::USD, 0),
+            tx(Direction::Returned, 40.0, MoneyType::USD, 1),
+            tx(Direction::Borrowed, 30.0, MoneyType::USD, 2),
+            tx(Direction::Repaid, 10.0, MoneyType::USD, 3),
+            tx(Direction::Gift, 5.0, MoneyType::USD, 4),
+            tx(Direction::Adjustment, -2.5, MoneyType::USD, 5),
+            tx(Direction::Lent, 200.0, MoneyType::GEL, 0),
+            tx(Direction::Lent, 50.0, MoneyType::GEL, 6),
+            // Returned in a currency nothing was lent in.
+            tx(Direction::Returned, 20.0, MoneyType::EUR, 7),
+        ];
+
+        let totals = calculate_currency_totals(&transactions);
+        assert_eq!(totals.len(), 3);
+
+        let usd = totals[&MoneyType::USD];
+        assert_eq!(
+            (usd.lent, usd.returned, usd.borrowed, usd.repaid),
+            (100.0, 40.0, 30.0, 10.0)
+        );
+        assert_eq!((usd.gifts, usd.adjustments), (5.0, -2.5));
+        assert_eq!(usd.owed_to_me(), 60.0);
+        assert_eq!(usd.i_owe(), 20.0);
+        assert_eq!(usd.net(), 37.5);
+
+        let gel = totals[&MoneyType::GEL];
+        assert_eq!((gel.lent, gel.returned, gel.net()), (250.0, 0.0, 250.0));
+
+        let eur = totals[&MoneyType::EUR];
+        assert_eq!((eur.lent, eur.returned), (0.0, 20.0));
+        assert_eq!(eur.net(), -20.0);
+        assert!(!totals.contains_key(&MoneyType::GBP));
+    }
+
+    #[test]
+    fn currency_totals_are_empty_without_transactions() {
+        assert!(calculate_currency_totals(&[]).is_empty());
+    }
+}
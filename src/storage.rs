@@ -0,0 +1,245 @@
+#[cfg(feature = "sqlite")]
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+use std::{fs, path::Path, sync::Mutex, time::SystemTime};
+
+use serde::Deserialize;
+
+use crate::{
+    database::{read_json, Database, DB_FILE},
+    settings::{Settings, StorageBackend},
+};
+
+pub const SQLITE_FILE: &str = "transactions.db";
+const REVISION_FILE: &str = "transactions.rev";
+
+static BACKEND: Mutex<Option<StorageBackend>> = Mutex::new(None);
+
+/// Where the database lives on disk. `Database` keeps the same load/save API
+/// whichever backend is active.
+pub trait Storage {
+    fn path(&self) -> &'static str;
+    fn read(&self) -> Option<Database>;
+    fn write(&self, db: &Database) -> Result<(), Box<dyn std::error::Error>>;
+    /// Revision last written, without reading the whole database.
+    fn revision(&self) -> Option<u64>;
+
+    fn modified(&self) -> Option<SystemTime> {
+        fs::metadata(self.path()).and_then(|m| m.modified()).ok()
+    }
+}
+
+/// Storage for the backend chosen in the settings.
+pub fn active() -> Box<dyn Storage> {
+    open(backend())
+}
+
+pub fn open(backend: StorageBackend) -> Box<dyn Storage> {
+    match backend {
+        StorageBackend::Json => Box::new(JsonStorage),
+        #[cfg(feature = "sqlite")]
+        StorageBackend::Sqlite => Box::new(SqliteStorage),
+        // Chosen in a build with SQLite; the JSON file is all this one can read.
+        #[cfg(not(feature = "sqlite"))]
+        StorageBackend::Sqlite => Box::new(JsonStorage),
+    }
+}
+
+/// The backend from the settings file, read once and then kept current by `select`.
+pub fn backend() -> StorageBackend {
+    *BACKEND
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(|| Settings::load().storage_backend)
+}
+
+pub fn select(backend: StorageBackend) {
+    *BACKEND.lock().unwrap_or_else(|e| e.into_inner()) = Some(backend);
+}
+
+/// Backend for settings saved before it was a setting: SQLite if its file is there.
+pub fn detect() -> StorageBackend {
+    if Path::new(SQLITE_FILE).exists() {
+        StorageBackend::Sqlite
+    } else {
+        StorageBackend::Json
+    }
+}
+
+#[derive(Deserialize)]
+struct Stamp {
+    #[serde(default)]
+    revision: u64,
+}
+
+/// The JSON file, with its revision copied to a small side file so checking it
+/// doesn't mean parsing every transaction.
+pub struct JsonStorage;
+
+impl Storage for JsonStorage {
+    fn path(&self) -> &'static str {
+        DB_FILE
+    }
+
+    fn read(&self) -> Option<Database> {
+        read_json(DB_FILE)
+    }
+
+    fn write(&self, db: &Database) -> Result<(), Box<dyn std::error::Error>> {
+        fs::write(DB_FILE, serde_json::to_string_pretty(db)?)?;
+        fs::write(REVISION_FILE, db.revision.to_string())?;
+        Ok(())
+    }
+
+    fn revision(&self) -> Option<u64> {
+        let modified = self.modified()?;
+        // Older than the data file when a save was cut short or an older version
+        // wrote it; the revision inside the file is the one to trust then.
+        let side_file = fs::metadata(REVISION_FILE)
+            .and_then(|m| m.modified())
+            .is_ok_and(|side_modified| side_modified >= modified);
+        if side_file {
+            if let Some(revision) = fs::read_to_string(REVISION_FILE)
+                .ok()
+                .and_then(|text| text.trim().parse().ok())
+            {
+                return Some(revision);
+            }
+        }
+        read_json::<Stamp>(DB_FILE).map(|stamp| stamp.revision)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub struct SqliteStorage;
+
+#[cfg(feature = "sqlite")]
+impl SqliteStorage {
+    fn open() -> rusqlite::Result<rusqlite::Connection> {
+        let conn = rusqlite::Connection::open(SQLITE_FILE)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+             CREATE TABLE IF NOT EXISTS transactions (position INTEGER PRIMARY KEY, data TEXT NOT NULL, hash INTEGER);
+             CREATE TABLE IF NOT EXISTS audit_log (position INTEGER PRIMARY KEY, data TEXT NOT NULL, hash INTEGER);",
+        )?;
+        // Files written before rows carried a hash; those rows are rewritten once.
+        for table in ["transactions", "audit_log"] {
+            let has_hash: bool = conn.query_row(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = 'hash'",
+                [table],
+                |row| row.get(0),
+            )?;
+            if !has_hash {
+                conn.execute(
+                    &format!("ALTER TABLE {} ADD COLUMN hash INTEGER", table),
+                    [],
+                )?;
+            }
+        }
+        Ok(conn)
+    }
+
+    /// Writes only the rows whose contents changed since the last save and drops
+    /// any past the end, so adding or editing one entry touches one row.
+    fn write_rows<T: serde::Serialize>(
+        tx: &rusqlite::Transaction,
+        table: &str,
+        rows: &[T],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let stored: HashMap<i64, Option<i64>> = {
+            let mut statement = tx.prepare(&format!("SELECT position, hash FROM {}", table))?;
+            let stored = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            stored.collect::<rusqlite::Result<_>>()?
+        };
+
+        let mut upsert = tx.prepare(&format!(
+            "INSERT INTO {} (position, data, hash) VALUES (?1, ?2, ?3)
+             ON CONFLICT(position) DO UPDATE SET data = excluded.data, hash = excluded.hash",
+            table
+        ))?;
+        for (position, row) in rows.iter().enumerate() {
+            let data = serde_json::to_string(row)?;
+            let mut hasher = DefaultHasher::new();
+            data.hash(&mut hasher);
+            let hash = hasher.finish() as i64;
+            if stored.get(&(position as i64)) != Some(&Some(hash)) {
+                upsert.execute(rusqlite::params![position as i64, data, hash])?;
+            }
+        }
+
+        tx.execute(
+            &format!("DELETE FROM {} WHERE position >= ?1", table),
+            [rows.len() as i64],
+        )?;
+        Ok(())
+    }
+
+    fn meta(conn: &rusqlite::Connection, key: &str) -> Option<String> {
+        conn.query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| {
+            row.get(0)
+        })
+        .ok()
+    }
+
+    fn rows<T: serde::de::DeserializeOwned>(
+        conn: &rusqlite::Connection,
+        table: &str,
+    ) -> Option<Vec<T>> {
+        let mut statement = conn
+            .prepare(&format!("SELECT data FROM {} ORDER BY position", table))
+            .ok()?;
+        let rows = statement
+            .query_map([], |row| row.get::<_, String>(0))
+            .ok()?;
+        rows.map(|data| serde_json::from_str(&data.ok()?).ok())
+            .collect()
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl Storage for SqliteStorage {
+    fn path(&self) -> &'static str {
+        SQLITE_FILE
+    }
+
+    fn read(&self) -> Option<Database> {
+        let conn = Self::open().ok()?;
+        let mut db = Database::default();
+        db.transactions = Self::rows(&conn, "transactions")?;
+        db.audit_log = Self::rows(&conn, "audit_log")?;
+        db.revision = Self::meta(&conn, "revision")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0);
+        db.saved_at = Self::meta(&conn, "saved_at").and_then(|value| value.parse().ok());
+        Some(db)
+    }
+
+    fn write(&self, db: &Database) -> Result<(), Box<dyn std::error::Error>> {
+        let mut conn = Self::open()?;
+        let tx = conn.transaction()?;
+        Self::write_rows(&tx, "transactions", &db.transactions)?;
+        Self::write_rows(&tx, "audit_log", &db.audit_log)?;
+        tx.execute(
+            "INSERT OR REPLACE INTO meta (key, value) VALUES ('revision', ?1)",
+            [db.revision.to_string()],
+        )?;
+        if let Some(saved_at) = db.saved_at {
+            tx.execute(
+                "INSERT OR REPLACE INTO meta (key, value) VALUES ('saved_at', ?1)",
+                [saved_at.to_rfc3339()],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn revision(&self) -> Option<u64> {
+        if !Path::new(SQLITE_FILE).exists() {
+            return None;
+        }
+        let conn = Self::open().ok()?;
+        Self::meta(&conn, "revision").and_then(|value| value.parse().ok())
+    }
+}